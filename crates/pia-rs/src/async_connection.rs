@@ -0,0 +1,132 @@
+use std::{
+    future, io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use futures_core::Stream;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    connection::{encode_message, Dispatcher},
+    event::{
+        client::{Call, ClientEvent},
+        daemon::{DaemonEvent, LocationChoice},
+        RequestId,
+    },
+    jsonrpc, DaemonConnectionBuilder, Error,
+};
+
+/// An async version of [`DaemonConnection`](crate::DaemonConnection) running on tokio.
+///
+/// Events from the daemon are read through the [`Stream`] implementation, which ends when the
+/// daemon closes the connection.
+#[derive(Debug)]
+pub struct AsyncDaemonConnection {
+    inner: jsonrpc::AsyncDaemonJSONRPCConnection,
+    dispatcher: Dispatcher,
+}
+impl AsyncDaemonConnection {
+    pub(crate) fn new(
+        inner: jsonrpc::AsyncDaemonJSONRPCConnection,
+        dispatcher: Dispatcher,
+    ) -> Self {
+        Self { inner, dispatcher }
+    }
+
+    /// Opens a new connection to the daemon with the default settings.
+    pub async fn connect() -> Result<Self, Error> {
+        DaemonConnectionBuilder::new().connect_async().await
+    }
+
+    /// Waits for the next event from the daemon. Returns `None` once the connection is closed.
+    pub async fn recv(&mut self) -> Option<Result<Box<DaemonEvent>, Error>> {
+        future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    /// Sends an event to the daemon. See [`DaemonConnection::send`](crate::DaemonConnection::send)
+    /// for how backpressure is handled; queued events go out while the stream is being polled.
+    pub async fn send(&mut self, event: ClientEvent) -> Result<(), Error> {
+        self.inner.write(&encode_message(None, event)?).await
+    }
+
+    /// Sends a request to the daemon without waiting for its reply. The reply is yielded by the
+    /// stream as a [`DaemonEvent::Response`] or [`DaemonEvent::Error`] with the returned id.
+    pub async fn request(&mut self, event: ClientEvent) -> Result<RequestId, Error> {
+        let id = self.dispatcher.next_id();
+        self.inner.write(&encode_message(Some(id), event)?).await?;
+        Ok(id)
+    }
+
+    /// Sends a request to the daemon and waits for its reply, for up to the
+    /// [call timeout](DaemonConnectionBuilder::call_timeout). Events that arrive in the meantime,
+    /// including ones that fail to decode, are kept and yielded by the stream afterwards.
+    pub async fn call<T: DeserializeOwned>(&mut self, call: Call<T>) -> Result<T, Error> {
+        let id = self.dispatcher.next_id();
+        self.inner
+            .write(&encode_message(Some(id), call.into_event())?)
+            .await?;
+        let timeout = self.dispatcher.call_timeout;
+        let wait = async {
+            loop {
+                let Some(bytes) = future::poll_fn(|cx| self.inner.poll_message(cx)).await? else {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                };
+                if let Some(result) =
+                    self.dispatcher
+                        .handle_message(self.inner.session_mut(), id, &bytes)
+                {
+                    return Ok::<_, Error>(result);
+                }
+            }
+        };
+        let result = tokio::time::timeout(timeout, wait)
+            .await
+            .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+        self.dispatcher.decode_result(result?)
+    }
+
+    /// Switches to `location` and connects to it. See
+    /// [`DaemonConnection::connect_to`](crate::DaemonConnection::connect_to).
+    pub async fn connect_to(&mut self, location: LocationChoice) -> Result<(), Error> {
+        self.call(Call::set_location(location)).await?;
+        self.call(Call::connect_vpn()).await
+    }
+
+    /// The JSON-RPC version the daemon is speaking, if it has sent any messages yet.
+    pub fn jsonrpc_version(&self) -> Option<&str> {
+        self.inner.session().jsonrpc_version()
+    }
+
+    /// Number of sent messages that the daemon hasn't acknowledged yet.
+    pub fn unacknowledged(&self) -> u16 {
+        self.inner.session().unacknowledged()
+    }
+
+    /// Number of messages queued because the daemon is falling behind.
+    pub fn queued(&self) -> usize {
+        self.inner.session().queued()
+    }
+
+    /// Number of bytes that were discarded because they weren't part of a valid frame.
+    pub fn dropped_bytes(&self) -> u64 {
+        self.inner.session().dropped_bytes()
+    }
+}
+
+impl Stream for AsyncDaemonConnection {
+    type Item = Result<Box<DaemonEvent>, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Some(event) = this.dispatcher.backlog.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        let Some(bytes) = ready!(this.inner.poll_message(cx))? else {
+            return Poll::Ready(None);
+        };
+        Poll::Ready(Some(
+            this.dispatcher.decode(this.inner.session_mut(), &bytes),
+        ))
+    }
+}
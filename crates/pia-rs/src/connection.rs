@@ -1,37 +1,397 @@
-use std::io;
+use std::{
+    collections::VecDeque,
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant, SystemTime},
+};
 
-use crate::{event, jsonrpc};
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
 
-pub use jsonrpc::TakeConnectionError;
+use crate::{
+    error::{self, DecodeError},
+    event::{
+        self,
+        client::{Call, ClientEvent},
+        daemon::{DaemonEvent, LocationChoice},
+        RequestId, RpcError,
+    },
+    jsonrpc, Error,
+};
+
+pub use jsonrpc::DEFAULT_SOCKET_PATH;
+
+/// Environment variable that, when set, overrides the default daemon socket path.
+pub const SOCKET_PATH_ENV_VAR: &str = "PIA_DAEMON_SOCKET";
+
+/// How long calls wait for a reply unless configured otherwise. Some calls, like logging in, wait
+/// on PIA's servers, so this is fairly generous.
+pub const DEFAULT_CALL_TIMEOUT: Duration = Duration::from_secs(30);
 
 #[derive(Debug)]
-pub struct DaemonConnection(jsonrpc::DaemonJSONRPCConnection);
+pub struct DaemonConnection {
+    inner: jsonrpc::DaemonJSONRPCConnection,
+    dispatcher: Dispatcher,
+}
 impl DaemonConnection {
-    fn new(inner: jsonrpc::DaemonJSONRPCConnection) -> Self {
-        Self(inner)
+    fn new(inner: jsonrpc::DaemonJSONRPCConnection, dispatcher: Dispatcher) -> Self {
+        Self { inner, dispatcher }
+    }
+
+    pub fn builder() -> DaemonConnectionBuilder {
+        DaemonConnectionBuilder::new()
+    }
+
+    pub fn poll(&mut self) -> Result<Box<DaemonEvent>, Error> {
+        if let Some(event) = self.dispatcher.backlog.pop_front() {
+            return event;
+        }
+        let bytes = self.inner.poll()?;
+        self.dispatcher.decode(self.inner.session_mut(), &bytes)
+    }
+
+    /// Sends an event to the daemon.
+    ///
+    /// If the daemon is falling behind, the event is queued and sent once the daemon acknowledges
+    /// earlier messages or reads what's already been written, which requires [`poll`](Self::poll)
+    /// to keep being called. If the queue is full, [`Error::Backpressure`] is returned and the
+    /// event is not sent.
+    pub fn send(&mut self, event: ClientEvent) -> Result<(), Error> {
+        self.inner.write(&encode_message(None, event)?)
+    }
+
+    /// Sends a request to the daemon without waiting for its reply. The reply is returned by
+    /// [`poll`](Self::poll) as a [`DaemonEvent::Response`] or [`DaemonEvent::Error`] with the
+    /// returned id.
+    pub fn request(&mut self, event: ClientEvent) -> Result<RequestId, Error> {
+        let id = self.dispatcher.next_id();
+        self.inner.write(&encode_message(Some(id), event)?)?;
+        Ok(id)
+    }
+
+    /// Sends a request to the daemon and waits for its reply.
+    ///
+    /// This blocks until the daemon replies, even if the connection is nonblocking, or until the
+    /// [call timeout](DaemonConnectionBuilder::call_timeout) passes, in which case a
+    /// [`TimedOut`](io::ErrorKind::TimedOut) [`Error::Transport`] is returned. Events that arrive
+    /// in the meantime, including ones that fail to decode, are kept and returned by later calls
+    /// to [`poll`](Self::poll).
+    pub fn call<T: DeserializeOwned>(&mut self, call: Call<T>) -> Result<T, Error> {
+        let id = self.dispatcher.next_id();
+        self.inner
+            .write(&encode_message(Some(id), call.into_event())?)?;
+        let deadline = Instant::now() + self.dispatcher.call_timeout;
+        loop {
+            let bytes = self.inner.poll_until(deadline)?;
+            if let Some(result) =
+                self.dispatcher
+                    .handle_message(self.inner.session_mut(), id, &bytes)
+            {
+                return self.dispatcher.decode_result(result?);
+            }
+        }
+    }
+
+    /// Switches to `location` and connects to it. If the VPN is already connected, it reconnects to
+    /// the new location.
+    pub fn connect_to(&mut self, location: LocationChoice) -> Result<(), Error> {
+        self.call(Call::set_location(location))?;
+        self.call(Call::connect_vpn())
+    }
+
+    /// The JSON-RPC version the daemon is speaking, if it has sent any messages yet.
+    pub fn jsonrpc_version(&self) -> Option<&str> {
+        self.inner.session().jsonrpc_version()
+    }
+
+    /// Number of sent messages that the daemon hasn't acknowledged yet.
+    pub fn unacknowledged(&self) -> u16 {
+        self.inner.session().unacknowledged()
+    }
+
+    /// Number of messages queued because the daemon is falling behind.
+    pub fn queued(&self) -> usize {
+        self.inner.session().queued()
+    }
+
+    /// Number of bytes that were discarded because they weren't part of a valid frame, e.g. when
+    /// the daemon restarts partway through writing a message.
+    pub fn dropped_bytes(&self) -> u64 {
+        self.inner.session().dropped_bytes()
+    }
+}
+
+/// Message handling shared by the sync and async connections: decoding, request ids and
+/// matching up replies.
+#[derive(Debug)]
+pub(crate) struct Dispatcher {
+    last_id: RequestId,
+    /// Events that arrived while waiting for a reply, and errors decoding them.
+    pub backlog: VecDeque<Result<Box<DaemonEvent>, Error>>,
+    /// How long to wait for a reply to a call.
+    pub call_timeout: Duration,
+    /// Where to save messages that fail to decode, if anywhere.
+    capture_dir: Option<PathBuf>,
+}
+impl Dispatcher {
+    pub fn new(call_timeout: Duration, capture_dir: Option<PathBuf>) -> Self {
+        Self {
+            last_id: RequestId::default(),
+            backlog: VecDeque::new(),
+            call_timeout,
+            capture_dir,
+        }
+    }
+
+    pub fn next_id(&mut self) -> RequestId {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    /// Handles a message received while waiting for the reply to `id`, returning the result if
+    /// this is that reply. Anything else, including messages that fail to decode, goes in the
+    /// backlog.
+    pub fn handle_message(
+        &mut self,
+        session: &mut jsonrpc::Session,
+        id: RequestId,
+        bytes: &[u8],
+    ) -> Option<Result<serde_json::Value, RpcError>> {
+        let event = match self.decode(session, bytes) {
+            Ok(event) => event,
+            Err(err) => {
+                self.backlog.push_back(Err(err));
+                return None;
+            }
+        };
+        match *event {
+            DaemonEvent::Response {
+                id: reply_id,
+                result,
+            } if reply_id == id => Some(Ok(result)),
+            DaemonEvent::Error {
+                id: Some(reply_id),
+                error,
+            } if reply_id == id => Some(Err(error)),
+            _ => {
+                self.backlog.push_back(Ok(event));
+                None
+            }
+        }
+    }
+
+    pub fn decode(
+        &self,
+        session: &mut jsonrpc::Session,
+        bytes: &[u8],
+    ) -> Result<Box<DaemonEvent>, Error> {
+        let event = decode_message(session, bytes).inspect_err(|err| self.capture(err))?;
+        if let DaemonEvent::Data([ref param]) = event {
+            param.errors.iter().for_each(|err| self.capture(err));
+        }
+        Ok(Box::new(event))
+    }
+
+    pub fn decode_result<T: DeserializeOwned>(
+        &self,
+        result: serde_json::Value,
+    ) -> Result<T, Error> {
+        // going through bytes (instead of `from_value`) gets us the payload and path
+        let bytes = serde_json::to_vec(&result).map_err(io::Error::from)?;
+        error::decode(&bytes).map_err(|err| {
+            let err = err.with_path_prefix("result");
+            self.capture(&err);
+            err.into()
+        })
+    }
+
+    /// Saves the payload of a decode error to the capture directory, if there is one. This is
+    /// best-effort; failing to save it doesn't affect the error.
+    fn capture(&self, err: &DecodeError) {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        if let Some(ref dir) = self.capture_dir {
+            let timestamp = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+            let _ = std::fs::create_dir_all(dir).and_then(|()| {
+                std::fs::write(
+                    dir.join(format!("pia-rs-{timestamp}-{count}.json")),
+                    err.payload(),
+                )
+            });
+        }
+    }
+}
+
+/// Every member a JSON-RPC message can have. Which ones are present determines what kind of
+/// message it is.
+#[derive(Deserialize)]
+struct RawMessage<'a> {
+    #[serde(rename = "jsonrpc")]
+    jsonrpc_version: String,
+    #[serde(default)]
+    id: Option<RequestId>,
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default, borrow)]
+    params: Option<&'a serde_json::value::RawValue>,
+    #[serde(default)]
+    result: Option<serde_json::Value>,
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+fn decode_message(
+    session: &mut jsonrpc::Session,
+    bytes: &[u8],
+) -> Result<DaemonEvent, DecodeError> {
+    let message: RawMessage = error::decode(bytes)?;
+    session.set_jsonrpc_version(&message.jsonrpc_version);
+    match (message.method, message.error, message.id) {
+        (Some(method), _, _) => DaemonEvent::from_method(&method, message.params)
+            .map_err(|err| err.with_path_prefix("params").with_payload(bytes)),
+        (None, Some(error), id) => Ok(DaemonEvent::Error { id, error }),
+        (None, None, Some(id)) => Ok(DaemonEvent::Response {
+            id,
+            result: message.result.unwrap_or_default(),
+        }),
+        (None, None, None) => Err(DecodeError::missing_field(bytes, "method")),
+    }
+}
+
+pub(crate) fn encode_message(id: Option<RequestId>, event: ClientEvent) -> Result<Vec<u8>, Error> {
+    serde_json::to_vec(&event::JSONRPCMessage {
+        jsonrpc_version: "2.0".to_owned(),
+        id,
+        event: Box::new(event),
+    })
+    .map_err(|err| io::Error::from(err).into())
+}
+
+/// Configures how to connect to the daemon.
+///
+/// The socket path is chosen in this order:
+/// 1. The path given to [`socket_path`](Self::socket_path), if any.
+/// 2. The environment variable named by [`env_var`](Self::env_var) ([`SOCKET_PATH_ENV_VAR`] by
+///    default), if it's set and [`no_env_var`](Self::no_env_var) wasn't used.
+/// 3. [`DEFAULT_SOCKET_PATH`].
+#[derive(Debug, Clone)]
+pub struct DaemonConnectionBuilder {
+    socket_path: Option<PathBuf>,
+    env_var: Option<OsString>,
+    connect_timeout: Option<Duration>,
+    call_timeout: Duration,
+    nonblocking: bool,
+    capture_dir: Option<PathBuf>,
+}
+impl DaemonConnectionBuilder {
+    pub fn new() -> Self {
+        Self {
+            socket_path: None,
+            env_var: Some(SOCKET_PATH_ENV_VAR.into()),
+            connect_timeout: None,
+            call_timeout: DEFAULT_CALL_TIMEOUT,
+            nonblocking: true,
+            capture_dir: None,
+        }
+    }
+
+    /// Connects to the socket at `path` instead of the default one.
+    pub fn socket_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.socket_path = Some(path.into());
+        self
+    }
+
+    /// Reads the socket path from the environment variable `name` if it's set.
+    pub fn env_var(mut self, name: impl Into<OsString>) -> Self {
+        self.env_var = Some(name.into());
+        self
     }
 
-    pub fn poll(&mut self) -> io::Result<Box<event::daemon::DaemonEvent>> {
-        let bytes = self.0.poll()?;
-        let res: Result<event::JSONRPCMessage<event::daemon::DaemonEvent>, serde_json::Error> =
-            serde_json::from_slice(&bytes);
-        if res.is_err() {
-            std::fs::write("/tmp/a.json", &bytes).unwrap();
+    /// Never reads the socket path from the environment.
+    pub fn no_env_var(mut self) -> Self {
+        self.env_var = None;
+        self
+    }
+
+    /// Keeps retrying for up to `timeout` if the daemon isn't accepting connections yet. By
+    /// default, only one attempt is made.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// How long [`DaemonConnection::call`] waits for the daemon to reply.
+    /// [`DEFAULT_CALL_TIMEOUT`] by default.
+    pub fn call_timeout(mut self, timeout: Duration) -> Self {
+        self.call_timeout = timeout;
+        self
+    }
+
+    /// Whether [`DaemonConnection::poll`] returns a [`WouldBlock`](Error::is_would_block) error when there are no
+    /// messages (the default) or waits for one.
+    pub fn nonblocking(mut self, nonblocking: bool) -> Self {
+        self.nonblocking = nonblocking;
+        self
+    }
+
+    /// The socket path that [`connect`](Self::connect) would use.
+    pub fn resolved_socket_path(&self) -> PathBuf {
+        if let Some(ref path) = self.socket_path {
+            return path.clone();
         }
-        Ok(res?.event)
+        self.env_var
+            .as_ref()
+            .and_then(std::env::var_os)
+            .filter(|path| !path.is_empty())
+            .map_or_else(|| Path::new(DEFAULT_SOCKET_PATH).to_owned(), PathBuf::from)
+    }
+
+    /// Saves messages from the daemon that fail to decode to `dir`, one file per message. The
+    /// payload is also available from the returned [`DecodeError`], but this catches ones that
+    /// happen while waiting for a reply in [`DaemonConnection::call`] too. For `data` events,
+    /// each object that fails to decode is saved on its own.
+    pub fn capture_failed_payloads(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.capture_dir = Some(dir.into());
+        self
     }
 
-    pub fn send(&mut self, event: event::client::ClientEvent) -> io::Result<()> {
-        let bytes = serde_json::to_vec(&event::JSONRPCMessage {
-            jsonrpc_version: "2.0".to_owned(),
-            event: Box::new(event),
-        })?;
-        self.0.write(&bytes)?;
-        Ok(())
+    pub fn connect(&self) -> Result<DaemonConnection, Error> {
+        let connection = jsonrpc::connect(
+            &self.resolved_socket_path(),
+            self.connect_timeout,
+            self.nonblocking,
+        )?;
+        Ok(DaemonConnection::new(
+            connection,
+            Dispatcher::new(self.call_timeout, self.capture_dir.clone()),
+        ))
+    }
+
+    /// Connects to the daemon asynchronously. The blocking mode is ignored.
+    #[cfg(feature = "tokio")]
+    pub async fn connect_async(&self) -> Result<crate::AsyncDaemonConnection, Error> {
+        let connection =
+            jsonrpc::connect_async(&self.resolved_socket_path(), self.connect_timeout).await?;
+        Ok(crate::AsyncDaemonConnection::new(
+            connection,
+            Dispatcher::new(self.call_timeout, self.capture_dir.clone()),
+        ))
+    }
+}
+impl Default for DaemonConnectionBuilder {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-pub fn take_connection() -> Result<DaemonConnection, TakeConnectionError> {
-    let connection = jsonrpc::take_connection()?;
-    Ok(DaemonConnection::new(connection))
+/// Opens a new connection to the daemon with the default settings. Connections are independent of
+/// each other, so this can be called any number of times.
+pub fn take_connection() -> Result<DaemonConnection, Error> {
+    DaemonConnection::builder().connect()
 }
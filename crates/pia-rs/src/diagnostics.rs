@@ -0,0 +1,99 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    net::IpAddr,
+    path::Path,
+    time::SystemTime,
+};
+
+use serde_json::Value;
+
+use crate::event::daemon::DaemonState;
+
+/// What redacted values are replaced with.
+const REDACTED: &str = "REDACTED";
+
+/// Properties that are redacted regardless of their value. Anything that looks like an IP address
+/// is redacted too, wherever it is.
+const REDACTED_KEYS: &[&str] = &[
+    // stored as numbers
+    "existingDNSServers",
+    // Wi-Fi network names in automation rules
+    "ssid",
+    // may include the custom proxy's credentials
+    "proxyCustom",
+    "username",
+    "password",
+];
+
+/// Serializes `state` with IP addresses, usernames and other identifying values replaced, so it
+/// can be shared with PIA support.
+pub fn redacted_state(state: &DaemonState) -> Value {
+    let mut value = serde_json::to_value(state).expect("DaemonState always serializes");
+    redact(&mut value);
+    value
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::String(s) if looks_like_ip(s) => *s = REDACTED.to_owned(),
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        Value::Object(map) => {
+            for (key, value) in map {
+                if REDACTED_KEYS.contains(&key.as_str()) {
+                    *value = REDACTED.into();
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether `s` is an IP address, optionally with a prefix length (`10.0.0.1/24`).
+fn looks_like_ip(s: &str) -> bool {
+    let addr = s.split_once('/').map_or(s, |(addr, _)| addr);
+    addr.parse::<IpAddr>().is_ok()
+}
+
+/// Writes a tar archive for a support ticket to `out`, containing the diagnostics report written
+/// by [`Call::write_diagnostics`](crate::event::client::Call::write_diagnostics) and
+/// [`redacted_state`] as `state.json`.
+///
+/// ```no_run
+/// use pia_rs::{event::{client::Call, daemon::DaemonState}, DaemonConnection};
+///
+/// fn save_bundle(conn: &mut DaemonConnection, state: &DaemonState) -> Result<(), pia_rs::Error> {
+///     let report = conn.call(Call::write_diagnostics())?;
+///     let out = std::fs::File::create("pia-diagnostics.tar")?;
+///     pia_rs::write_diagnostics_bundle(out, &report, state)?;
+///     Ok(())
+/// }
+/// ```
+pub fn write_diagnostics_bundle<W: Write>(
+    out: W,
+    report: &Path,
+    state: &DaemonState,
+) -> io::Result<W> {
+    let mut builder = tar::Builder::new(out);
+
+    let name = report.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "report path has no file name")
+    })?;
+    builder.append_file(name, &mut File::open(report)?)?;
+
+    let state = serde_json::to_vec_pretty(&redacted_state(state))?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(state.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs()),
+    );
+    header.set_cksum();
+    builder.append_data(&mut header, "state.json", state.as_slice())?;
+
+    builder.into_inner()
+}
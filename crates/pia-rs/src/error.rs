@@ -0,0 +1,146 @@
+use std::{fmt, io};
+
+use serde::Deserialize;
+
+use crate::event::RpcError;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Reading from or writing to the daemon's socket failed.
+    ///
+    /// On a nonblocking connection, [`DaemonConnection::poll`](crate::DaemonConnection::poll)
+    /// returns an error of kind [`io::ErrorKind::WouldBlock`] when there's nothing to read; see
+    /// [`is_would_block`](Self::is_would_block).
+    Transport(io::Error),
+    /// A message of `len` bytes is too large (or small) to fit in a frame.
+    Framing { len: usize },
+    /// A message from the daemon couldn't be decoded.
+    Decode(DecodeError),
+    /// The daemon has fallen too far behind in reading or acknowledging messages and the send
+    /// queue is full.
+    /// The message was not sent; try again after polling for a while.
+    Backpressure { unacknowledged: u16, queued: usize },
+    /// The daemon replied to a request with an error.
+    Rpc(RpcError),
+}
+
+impl Error {
+    /// Whether this is a nonblocking read that had nothing to return.
+    pub fn is_would_block(&self) -> bool {
+        matches!(self, Self::Transport(err) if err.kind() == io::ErrorKind::WouldBlock)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(inner: io::Error) -> Self {
+        Self::Transport(inner)
+    }
+}
+impl From<DecodeError> for Error {
+    fn from(inner: DecodeError) -> Self {
+        Self::Decode(inner)
+    }
+}
+impl From<RpcError> for Error {
+    fn from(inner: RpcError) -> Self {
+        Self::Rpc(inner)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(err) => err.fmt(f),
+            Self::Framing { len } => write!(f, "message of {len} bytes can't be framed"),
+            Self::Decode(err) => err.fmt(f),
+            Self::Backpressure {
+                unacknowledged,
+                queued,
+            } => write!(
+                f,
+                "daemon is falling behind ({unacknowledged} unacknowledged messages, {queued} queued)"
+            ),
+            Self::Rpc(err) => err.fmt(f),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Transport(err) => Some(err),
+            Self::Decode(err) => Some(err),
+            Self::Rpc(err) => Some(err),
+            Self::Framing { .. } | Self::Backpressure { .. } => None,
+        }
+    }
+}
+
+/// A message that couldn't be decoded, along with where decoding went wrong.
+#[derive(Debug)]
+pub struct DecodeError {
+    payload: Vec<u8>,
+    path: String,
+    inner: serde_json::Error,
+}
+
+impl DecodeError {
+    /// The raw JSON that failed to decode.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+    /// Path to the field that failed to decode, e.g. `params[0].state.connectedConfig.dnsType`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+    pub fn inner(&self) -> &serde_json::Error {
+        &self.inner
+    }
+
+    pub(crate) fn missing_field(payload: &[u8], field: &'static str) -> Self {
+        Self {
+            payload: payload.to_vec(),
+            path: String::new(),
+            inner: serde::de::Error::missing_field(field),
+        }
+    }
+
+    pub(crate) fn with_payload(mut self, payload: &[u8]) -> Self {
+        self.payload = payload.to_vec();
+        self
+    }
+    pub(crate) fn with_path_prefix(mut self, prefix: &str) -> Self {
+        self.path = match self.path.as_str() {
+            "" | "." => prefix.to_owned(),
+            path if path.starts_with('[') => format!("{prefix}{path}"),
+            path => format!("{prefix}.{path}"),
+        };
+        self
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to decode `{}`: {}", self.path, self.inner)
+    }
+}
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.inner)
+    }
+}
+
+/// Deserializes `json`, keeping track of where it fails.
+pub(crate) fn decode<'de, T: Deserialize<'de>>(json: &'de [u8]) -> Result<T, DecodeError> {
+    let mut deserializer = serde_json::Deserializer::from_slice(json);
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(|err| DecodeError {
+        payload: json.to_vec(),
+        path: err.path().to_string(),
+        inner: err.into_inner(),
+    })?;
+    deserializer.end().map_err(|inner| DecodeError {
+        payload: json.to_vec(),
+        path: String::new(),
+        inner,
+    })?;
+    Ok(value)
+}
@@ -1,8 +1,261 @@
+use std::{
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
 use serde_derive::{Deserialize, Serialize};
 
+use super::daemon::{
+    AutomationRule, AutomationRuleCondition, DaemonSettings, LocationChoice, PartialSettings,
+    SplitTunnelRule, SplitTunnelSubnetRule,
+};
+use crate::ServerCode;
+
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(tag = "method", content = "params", rename_all = "camelCase")]
 pub enum ClientEvent {
     ConnectVPN,
     DisconnectVPN,
+    /// Changes the given settings, leaving the rest alone. If the second parameter is set and
+    /// the changes require it, the daemon reconnects to apply them.
+    ApplySettings(Box<PartialSettings>, bool),
+    /// Resets all settings to their defaults.
+    ResetSettings,
+    /// Logs in with a username and password.
+    Login(String, String),
+    /// Sends a login link to an email address.
+    EmailLogin([String; 1]),
+    /// Logs in with an API token, e.g. one from another device.
+    SetToken([String; 1]),
+    /// Logs out, disconnecting first if the VPN is connected.
+    Logout,
+    /// Disconnects for the given number of seconds, then reconnects.
+    StartSnooze([u64; 1]),
+    /// Ends a snooze early and reconnects.
+    StopSnooze,
+    /// Adds a dedicated IP by its token.
+    AddDedicatedIp([String; 1]),
+    /// Removes a dedicated IP by the ID of its region.
+    RemoveDedicatedIp([ServerCode; 1]),
+    /// Starts downloading the installer for the available update.
+    DownloadUpdate,
+    /// Cancels downloading the installer.
+    CancelDownloadUpdate,
+    /// Writes a diagnostics report (logs, network configuration etc.) for PIA support.
+    WriteDiagnostics,
+}
+
+/// A [`ClientEvent`] sent as a request, along with the type of result the daemon replies with.
+///
+/// Pass these to [`DaemonConnection::call`](crate::DaemonConnection::call).
+#[derive(Debug)]
+pub struct Call<T> {
+    event: ClientEvent,
+    result: PhantomData<fn() -> T>,
+}
+
+impl<T> Call<T> {
+    /// Creates a call for an arbitrary event. It's up to the caller to get the result type right.
+    pub fn new(event: ClientEvent) -> Self {
+        Self {
+            event,
+            result: PhantomData,
+        }
+    }
+
+    pub fn event(&self) -> &ClientEvent {
+        &self.event
+    }
+    pub fn into_event(self) -> ClientEvent {
+        self.event
+    }
+}
+
+impl Call<()> {
+    pub fn connect_vpn() -> Self {
+        Self::new(ClientEvent::ConnectVPN)
+    }
+    pub fn disconnect_vpn() -> Self {
+        Self::new(ClientEvent::DisconnectVPN)
+    }
+    /// Changes the given settings. Changes that need a reconnect take effect the next time the
+    /// VPN connects; see [`DaemonState::needs_reconnect`](crate::event::daemon::DaemonState::needs_reconnect).
+    pub fn apply_settings(settings: PartialSettings) -> Self {
+        Self::new(ClientEvent::ApplySettings(Box::new(settings), false))
+    }
+    /// Changes the given settings, reconnecting if the VPN is connected and the changes need it.
+    pub fn apply_settings_and_reconnect(settings: PartialSettings) -> Self {
+        Self::new(ClientEvent::ApplySettings(Box::new(settings), true))
+    }
+    /// Changes the region to connect to, reconnecting if the VPN is connected (like
+    /// `piactl set region`). This doesn't connect if the VPN is disconnected; see
+    /// [`DaemonConnection::connect_to`](crate::DaemonConnection::connect_to) for that.
+    pub fn set_location(location: LocationChoice) -> Self {
+        Self::apply_settings_and_reconnect(PartialSettings {
+            location: Some(location),
+            ..Default::default()
+        })
+    }
+    pub fn reset_settings() -> Self {
+        Self::new(ClientEvent::ResetSettings)
+    }
+
+    pub fn set_split_tunnel_enabled(enabled: bool) -> Self {
+        Self::apply_settings(PartialSettings {
+            split_tunnel_enabled: Some(enabled),
+            ..Default::default()
+        })
+    }
+    /// Replaces all of the app rules. The daemon only accepts the whole list, so to change one
+    /// rule use [`add_split_tunnel_rule`](Self::add_split_tunnel_rule) or
+    /// [`remove_split_tunnel_rule`](Self::remove_split_tunnel_rule) with the current settings.
+    pub fn set_split_tunnel_rules(rules: Vec<SplitTunnelRule>) -> Self {
+        Self::apply_settings(PartialSettings {
+            split_tunnel_rules: Some(rules),
+            ..Default::default()
+        })
+    }
+    /// Adds `rule` to the rules in `settings`, replacing any existing rule for the same app.
+    pub fn add_split_tunnel_rule(settings: &DaemonSettings, rule: SplitTunnelRule) -> Self {
+        let mut rules = settings.split_tunnel_rules.clone();
+        match rules.iter_mut().find(|existing| existing.path == rule.path) {
+            Some(existing) => *existing = rule,
+            None => rules.push(rule),
+        }
+        Self::set_split_tunnel_rules(rules)
+    }
+    /// Removes the rule for the app at `path` from the rules in `settings`.
+    pub fn remove_split_tunnel_rule(settings: &DaemonSettings, path: &Path) -> Self {
+        let mut rules = settings.split_tunnel_rules.clone();
+        rules.retain(|rule| rule.path != path);
+        Self::set_split_tunnel_rules(rules)
+    }
+    /// Replaces all of the subnet rules.
+    pub fn set_bypass_subnets(subnets: Vec<SplitTunnelSubnetRule>) -> Self {
+        Self::apply_settings(PartialSettings {
+            bypass_subnets: Some(subnets),
+            ..Default::default()
+        })
+    }
+    /// Adds `rule` to the subnet rules in `settings`, replacing any existing rule for the same
+    /// subnet.
+    pub fn add_bypass_subnet(settings: &DaemonSettings, rule: SplitTunnelSubnetRule) -> Self {
+        let mut subnets = settings.bypass_subnets.clone();
+        match subnets
+            .iter_mut()
+            .find(|existing| existing.subnet == rule.subnet)
+        {
+            Some(existing) => *existing = rule,
+            None => subnets.push(rule),
+        }
+        Self::set_bypass_subnets(subnets)
+    }
+    /// Removes the rule for `subnet` from the subnet rules in `settings`.
+    pub fn remove_bypass_subnet(settings: &DaemonSettings, subnet: &str) -> Self {
+        let mut subnets = settings.bypass_subnets.clone();
+        subnets.retain(|rule| rule.subnet != subnet);
+        Self::set_bypass_subnets(subnets)
+    }
+    /// Sets the "Name Servers" rule; see
+    /// [`DaemonSettings::split_tunnel_dns`](crate::event::daemon::DaemonSettings::split_tunnel_dns).
+    pub fn set_split_tunnel_dns(follow_app_rules: bool) -> Self {
+        Self::apply_settings(PartialSettings {
+            split_tunnel_dns: Some(follow_app_rules),
+            ..Default::default()
+        })
+    }
+
+    pub fn set_automation_enabled(enabled: bool) -> Self {
+        Self::apply_settings(PartialSettings {
+            automation_enabled: Some(enabled),
+            ..Default::default()
+        })
+    }
+    /// Replaces all of the automation rules. Like the split tunnel rules, the daemon only accepts
+    /// the whole list.
+    pub fn set_automation_rules(rules: Vec<AutomationRule>) -> Self {
+        Self::apply_settings(PartialSettings {
+            automation_rules: Some(rules),
+            ..Default::default()
+        })
+    }
+    /// Adds `rule` to the rules in `settings`, replacing any existing rule with the same
+    /// condition. See
+    /// [`DaemonState::rule_for_current_network`](crate::event::daemon::DaemonState::rule_for_current_network)
+    /// to make a rule for the current network.
+    pub fn add_automation_rule(settings: &DaemonSettings, rule: AutomationRule) -> Self {
+        let mut rules = settings.automation_rules.clone();
+        match rules
+            .iter_mut()
+            .find(|existing| existing.condition == rule.condition)
+        {
+            Some(existing) => *existing = rule,
+            None => rules.push(rule),
+        }
+        Self::set_automation_rules(rules)
+    }
+    /// Removes the rule with `condition` from the rules in `settings`.
+    pub fn remove_automation_rule(
+        settings: &DaemonSettings,
+        condition: &AutomationRuleCondition,
+    ) -> Self {
+        let mut rules = settings.automation_rules.clone();
+        rules.retain(|rule| rule.condition != *condition);
+        Self::set_automation_rules(rules)
+    }
+
+    /// Logs in. Account information comes in through
+    /// [`DataEventParam::account`](crate::event::daemon::DataEventParam::account) once it
+    /// succeeds; if the credentials are wrong, the daemon replies with an error.
+    pub fn login(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self::new(ClientEvent::Login(username.into(), password.into()))
+    }
+    /// Asks PIA to email a login link to `email`. The daemon replies once the email is sent.
+    pub fn email_login(email: impl Into<String>) -> Self {
+        Self::new(ClientEvent::EmailLogin([email.into()]))
+    }
+    pub fn set_token(token: impl Into<String>) -> Self {
+        Self::new(ClientEvent::SetToken([token.into()]))
+    }
+    pub fn logout() -> Self {
+        Self::new(ClientEvent::Logout)
+    }
+
+    /// Disconnects for `duration` (rounded down to seconds), then reconnects. The VPN needs to be
+    /// connected. The progress is reported in
+    /// [`DaemonState::snooze_end_time`](crate::event::daemon::DaemonState::snooze_end_time).
+    pub fn start_snooze(duration: Duration) -> Self {
+        Self::new(ClientEvent::StartSnooze([duration.as_secs()]))
+    }
+    pub fn stop_snooze() -> Self {
+        Self::new(ClientEvent::StopSnooze)
+    }
+
+    /// Adds a dedicated IP. The daemon replies with an error if the token is invalid or expired.
+    /// Once added, it shows up in
+    /// [`DaemonState::dedicated_ip_locations`](crate::event::daemon::DaemonState::dedicated_ip_locations).
+    pub fn add_dedicated_ip(token: impl Into<String>) -> Self {
+        Self::new(ClientEvent::AddDedicatedIp([token.into()]))
+    }
+    /// Removes a dedicated IP, given the ID of the region created for it.
+    pub fn remove_dedicated_ip(region: ServerCode) -> Self {
+        Self::new(ClientEvent::RemoveDedicatedIp([region]))
+    }
+
+    /// Starts downloading the available update. Progress is reported through
+    /// [`DaemonState::update_status`](crate::event::daemon::DaemonState::update_status).
+    pub fn download_update() -> Self {
+        Self::new(ClientEvent::DownloadUpdate)
+    }
+    pub fn cancel_download_update() -> Self {
+        Self::new(ClientEvent::CancelDownloadUpdate)
+    }
+}
+
+impl Call<PathBuf> {
+    /// Asks the daemon to write a diagnostics report. Returns the path of the report file.
+    pub fn write_diagnostics() -> Self {
+        Self::new(ClientEvent::WriteDiagnostics)
+    }
 }
@@ -1,48 +1,182 @@
-use std::{collections::HashMap, net::Ipv4Addr};
+use std::{collections::HashMap, net::IpAddr};
 
+use serde::{
+    de::{self, DeserializeOwned},
+    ser,
+};
 use serde_derive::{Deserialize, Serialize};
+use serde_json::value::RawValue;
 
-use crate::util::ServerMap;
+use crate::{
+    error::{self, DecodeError},
+    util::ServerMap,
+    ServerCode,
+};
 
-use super::{util::Location, UnixTime};
+use super::{
+    partial::partial_struct, util::Location, PartialDaemonState, PartialSettings, UnixTime,
+};
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Changed properties, grouped by the daemon object they belong to.
+///
+/// Each object is decoded on its own, so one that fails to decode doesn't affect the others. It's
+/// left as `None`, and the error is added to [`errors`](Self::errors).
+#[derive(Serialize, Debug, Default)]
 pub struct DataEventParam {
+    pub account: Option<PartialAccount>,
+    pub data: Option<Box<PartialDaemonData>>,
+    pub settings: Option<Box<PartialSettings>>,
+    pub state: Option<Box<PartialDaemonState>>,
+    /// Errors from objects that failed to decode. Paths start from the event's params, e.g.
+    /// `params[0].settings.cipher`, and the payload is the object that failed.
+    #[serde(skip)]
+    pub errors: Vec<DecodeError>,
+}
+
+impl<'de> de::Deserialize<'de> for DataEventParam {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct RawObjects {
+            #[serde(default)]
+            account: Option<Box<RawValue>>,
+            #[serde(default)]
+            data: Option<Box<RawValue>>,
+            #[serde(default)]
+            settings: Option<Box<RawValue>>,
+            #[serde(default)]
+            state: Option<Box<RawValue>>,
+        }
+
+        let raw = RawObjects::deserialize(deserializer)?;
+        let mut errors = Vec::new();
+        Ok(Self {
+            account: decode_object(raw.account, "account", &mut errors),
+            data: decode_object(raw.data, "data", &mut errors),
+            settings: decode_object(raw.settings, "settings", &mut errors),
+            state: decode_object(raw.state, "state", &mut errors),
+            errors,
+        })
+    }
+}
+
+fn decode_object<T: DeserializeOwned>(
+    raw: Option<Box<RawValue>>,
+    name: &str,
+    errors: &mut Vec<DecodeError>,
+) -> Option<T> {
+    let raw = raw.filter(|raw| raw.get() != "null")?;
+    error::decode(raw.get().as_bytes())
+        .map_err(|err| errors.push(err.with_path_prefix(name)))
+        .ok()
+}
+
+partial_struct! {
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    pub struct AccountData => PartialAccount {
+        pub active: bool,
+        pub canceled: bool,
+        pub days_remaining: u32,
+        pub expiration_time: UnixTime,
+        pub expire_alert: bool,
+        pub expired: bool,
+        pub logged_in: bool,
+        pub needs_payment: bool,
+        pub plan: String,
+        pub recurring: bool,
+        #[serde(rename = "renewURL")]
+        pub renew_url: String,
+        pub renewable: bool,
+        pub username: String,
+        /// Dedicated IPs added to this installation with
+        /// [`Call::add_dedicated_ip`](crate::event::client::Call::add_dedicated_ip).
+        pub dedicated_ips: Vec<AccountDedicatedIp> = Vec::new(),
+    }
+}
+
+/// A dedicated IP, as stored in the account.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDedicatedIp {
+    /// ID of the region created for this dedicated IP.
+    pub id: ServerCode,
+    /// ID of the region the dedicated IP is in.
     #[serde(default)]
-    pub account: Option<serde_json::Map<String, serde_json::Value>>,
+    pub region_id: ServerCode,
     #[serde(default)]
-    pub data: Option<serde_json::Map<String, serde_json::Value>>,
+    pub ip: String,
+    /// Common name of the server.
     #[serde(default)]
-    pub state: Option<serde_json::Map<String, serde_json::Value>>,
+    pub cn: String,
+    /// When the dedicated IP expires, in seconds since the Unix epoch. 0 if unknown.
+    #[serde(default)]
+    pub expire: u64,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct AccountData {
-    pub active: bool,
-    pub canceled: bool,
-    pub days_remaining: u32,
-    pub expiration_time: UnixTime,
-    pub expire_alert: bool,
-    pub expired: bool,
-    pub logged_in: bool,
-    pub needs_payment: bool,
-    pub plan: String,
-    pub recurring: bool,
-    #[serde(rename = "renewURL")]
-    pub renew_url: String,
-    pub renewable: bool,
-    pub username: String,
+partial_struct! {
+    /// The daemon's cached data, mostly what it fetched from PIA's servers. Clients can't change
+    /// any of this.
+    ///
+    /// Everything except the latencies is optional, since which caches exist depends on the
+    /// daemon version.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    pub struct InnerData => PartialDaemonData {
+        /// Latency measurements in milliseconds, by region.
+        pub modern_latencies: ServerMap<u32>,
+        /// The last regions list fetched, with the servers for each protocol. This is empty until
+        /// the daemon first fetches it.
+        pub cached_modern_regions_list: RegionsList = RegionsList::default(),
+        /// The last Shadowsocks server list fetched.
+        pub cached_modern_shadowsocks_list: Vec<ShadowsocksServer> = Vec::new(),
+        /// The raw regions metadata (display names, coordinates etc). The parsed version is in
+        /// [`DaemonState::regions_metadata`](super::DaemonState::regions_metadata).
+        pub modern_region_meta: serde_json::Value = serde_json::Value::Null,
+        /// Feature flags enabled on the release channel.
+        pub flags: Vec<String> = Vec::new(),
+        /// Start type of the Windows DNS cache service before the daemon changed it, so it can be
+        /// restored. `-1` if it hasn't been changed; Windows only.
+        pub win_dnscache_original_start: i32 = -1,
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct InnerData {
-    // TODO
-    pub modern_latencies: ServerMap<u32>,
+impl InnerData {
+    /// A region from the regions list.
+    pub fn region(&self, id: &ServerCode) -> Option<&ServerRegion> {
+        self.cached_modern_regions_list
+            .regions
+            .iter()
+            .find(|region| region.id == *id)
+    }
+
+    /// The servers in a region for a protocol, e.g. its WireGuard servers.
+    pub fn servers(&self, id: &ServerCode, kind: &VPNConnectionType) -> &[ServerInfo] {
+        self.region(id)
+            .and_then(|region| region.servers.get(kind))
+            .map_or(&[], Vec::as_slice)
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// PIA's regions list, as fetched from the server list API.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct RegionsList {
+    /// Ports available for each protocol. These apply to every server.
+    #[serde(default)]
+    pub groups: HashMap<VPNConnectionType, Vec<ServerGroup>>,
+    #[serde(default)]
+    pub regions: Vec<ServerRegion>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ServerGroup {
+    pub name: String,
+    pub ports: Vec<u16>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 // these fields are snake case for some reason?
 pub struct ServerRegion {
     // ???
@@ -54,42 +188,82 @@ pub struct ServerRegion {
     // TODO: document
     pub geo: bool,
     /// Region ID.
-    pub id: String,
+    pub id: ServerCode,
     /// Region display name.
     pub name: String,
     // idk what these do
     pub offline: bool,
     pub port_forward: bool,
     /// Server info. Contains the IP addresses to actually connect to
-    pub servers: HashMap<VPNConnectionType, ServerInfo>,
+    pub servers: HashMap<VPNConnectionType, Vec<ServerInfo>>,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
-#[serde(rename_all = "lowercase")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum VPNConnectionType {
     IKEv2,
     // TODO: what is this?
     Meta,
-    #[serde(rename = "ovpntcp")]
     OpenVPNTCP,
-    #[serde(rename = "ovpnudp")]
     OpenVPNUDP,
-    #[serde(rename = "wg")]
     WireGuard,
+    /// A group this crate doesn't know about, e.g. `proxyss` for Shadowsocks.
+    Other(String),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl VPNConnectionType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::IKEv2 => "ikev2",
+            Self::Meta => "meta",
+            Self::OpenVPNTCP => "ovpntcp",
+            Self::OpenVPNUDP => "ovpnudp",
+            Self::WireGuard => "wg",
+            Self::Other(kind) => kind,
+        }
+    }
+}
+
+impl ser::Serialize for VPNConnectionType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.as_str().serialize(serializer)
+    }
+}
+impl<'de> de::Deserialize<'de> for VPNConnectionType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(match s.as_str() {
+            "ikev2" => Self::IKEv2,
+            "meta" => Self::Meta,
+            "ovpntcp" => Self::OpenVPNTCP,
+            "ovpnudp" => Self::OpenVPNUDP,
+            "wg" => Self::WireGuard,
+            _ => Self::Other(s),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct ServerInfo {
     /// Common name.
     pub cn: String,
     /// IP address.
-    pub ip: Ipv4Addr,
+    pub ip: IpAddr,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct Settings {
-    // TODO
+/// A server from the Shadowsocks server list.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ShadowsocksServer {
+    pub region: ServerCode,
+    pub host: String,
+    pub port: u16,
+    pub key: String,
+    pub cipher: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -103,7 +277,7 @@ pub struct State {
     available_locations: ServerMap<Location>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "PascalCase")]
 
 pub enum ConnectionState {
@@ -116,3 +290,67 @@ pub enum ConnectionState {
     Connected,
     Disconnecting,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trimmed from a regions list fetched by the daemon.
+    const REGIONS_LIST: &str = r#"{
+        "groups": {
+            "ikev2": [{"name": "ikev2", "ports": [500, 4500]}],
+            "meta": [{"name": "meta", "ports": [443, 8080]}],
+            "ovpntcp": [{"name": "openvpn_tcp", "ports": [80, 443, 853, 8443]}],
+            "ovpnudp": [{"name": "openvpn_udp", "ports": [8080, 853, 123, 53]}],
+            "proxysocks": [{"name": "socks", "ports": [1080]}],
+            "proxyss": [{"name": "shadowsocks", "ports": [443]}],
+            "wg": [{"name": "wireguard", "ports": [1337]}]
+        },
+        "regions": [{
+            "id": "us_california",
+            "name": "US California",
+            "country": "US",
+            "auto_region": true,
+            "dns": "us-california.privacy.network",
+            "port_forward": false,
+            "geo": false,
+            "offline": false,
+            "servers": {
+                "ikev2": [{"ip": "102.129.145.30", "cn": "losangeles403"}],
+                "meta": [{"ip": "102.129.145.2", "cn": "losangeles403"}],
+                "ovpntcp": [{"ip": "102.129.145.25", "cn": "losangeles403"}],
+                "ovpnudp": [{"ip": "102.129.145.6", "cn": "losangeles403"}],
+                "wg": [{"ip": "102.129.145.14", "cn": "losangeles403"}]
+            }
+        }]
+    }"#;
+
+    #[test]
+    fn regions_list() {
+        let list: RegionsList = serde_json::from_str(REGIONS_LIST).unwrap();
+        assert_eq!(
+            list.groups[&VPNConnectionType::Other("proxyss".to_owned())],
+            [ServerGroup {
+                name: "shadowsocks".to_owned(),
+                ports: vec![443],
+            }]
+        );
+        assert_eq!(list.groups[&VPNConnectionType::WireGuard][0].ports, [1337]);
+
+        let region = &list.regions[0];
+        assert_eq!(region.id.as_str(), "us_california");
+        assert_eq!(
+            region.servers[&VPNConnectionType::WireGuard],
+            [ServerInfo {
+                cn: "losangeles403".to_owned(),
+                ip: "102.129.145.14".parse().unwrap(),
+            }]
+        );
+
+        let reserialized = serde_json::to_value(&list).unwrap();
+        assert_eq!(
+            serde_json::from_value::<RegionsList>(reserialized).unwrap(),
+            list
+        );
+    }
+}
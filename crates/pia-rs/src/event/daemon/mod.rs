@@ -1,14 +1,72 @@
 mod data;
+mod network;
+mod notifications;
+mod partial;
+mod settings;
 mod state;
+mod update;
 pub use data::*;
+pub use network::NetworkSnapshot;
+pub use notifications::{Notification, NotificationKind, Severity};
+pub use partial::MissingField;
+pub(crate) use partial::PartialObject;
+pub use settings::*;
 pub use state::*;
+pub use update::UpdateStatus;
 
-use serde_derive::{Deserialize, Serialize};
 mod util;
-pub use util::{OptionalIpv4Addr, UnixTime};
+pub use util::{
+    MonotonicTime, NumericIpv4Addr, OptionalIpAddr, OptionalIpv4Addr, OptionalIpv4Prefix,
+    OptionalUnixTime, PortForward, SnoozeState, UnixTime,
+};
 
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(tag = "method", content = "params", rename_all = "camelCase")]
+use super::{RequestId, RpcError};
+use crate::error::{self, DecodeError};
+
+#[derive(Debug)]
 pub enum DaemonEvent {
+    /// Changes to the daemon's properties.
     Data([data::DataEventParam; 1]),
+    /// A successful reply to a request sent with
+    /// [`DaemonConnection::request`](crate::DaemonConnection::request).
+    Response {
+        id: RequestId,
+        result: serde_json::Value,
+    },
+    /// An error reply. `id` is `None` if the daemon couldn't tell which request it was for, e.g.
+    /// because the request couldn't be parsed.
+    Error {
+        id: Option<RequestId>,
+        error: RpcError,
+    },
+    /// A notification for a method this crate doesn't know about yet.
+    Unknown {
+        method: String,
+        params: serde_json::Value,
+    },
+}
+
+impl DaemonEvent {
+    /// Decodes a notification from a JSON-RPC method name and its params.
+    pub(crate) fn from_method(
+        method: &str,
+        params: Option<&serde_json::value::RawValue>,
+    ) -> Result<Self, DecodeError> {
+        let params = params.map_or("null", |params| params.get()).as_bytes();
+        Ok(match method {
+            "data" => {
+                let [mut param]: [data::DataEventParam; 1] = error::decode(params)?;
+                param.errors = param
+                    .errors
+                    .into_iter()
+                    .map(|err| err.with_path_prefix("params[0]"))
+                    .collect();
+                Self::Data([param])
+            }
+            _ => Self::Unknown {
+                method: method.to_owned(),
+                params: error::decode(params)?,
+            },
+        })
+    }
 }
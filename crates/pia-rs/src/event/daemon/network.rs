@@ -0,0 +1,51 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use ipnet::Ipv4Net;
+
+use super::DaemonState;
+
+/// The network configuration from before the VPN connected, as recorded by the daemon. The
+/// daemon fills this in while connecting, so it's mostly empty until the VPN first connects.
+///
+/// Compare snapshots to see what changed, e.g. after switching networks.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NetworkSnapshot {
+    /// Name of the default interface, e.g. `eth0`.
+    pub interface: Option<String>,
+    /// The interface's address and network prefix.
+    pub interface_net: Option<Ipv4Net>,
+    pub gateway: Option<Ipv4Addr>,
+    pub interface_ip6: Option<IpAddr>,
+    pub gateway_ip6: Option<IpAddr>,
+    pub mtu: u32,
+    pub mtu6: u32,
+    pub dns_servers: Vec<Ipv4Addr>,
+}
+
+impl DaemonState {
+    /// The original interface's address and network prefix together, e.g. `192.168.1.10/24`.
+    pub fn original_interface_net(&self) -> Option<Ipv4Net> {
+        Ipv4Net::new(
+            self.original_interface_ip.0?,
+            self.original_interface_net_prefix.0?,
+        )
+        .ok()
+    }
+
+    pub fn network_snapshot(&self) -> NetworkSnapshot {
+        NetworkSnapshot {
+            interface: Some(self.original_interface.clone()).filter(|name| !name.is_empty()),
+            interface_net: self.original_interface_net(),
+            gateway: self.original_gateway_ip.0,
+            interface_ip6: self.original_interface_ip6.0,
+            gateway_ip6: self.original_gateway_ip6.0,
+            mtu: self.original_mtu,
+            mtu6: self.original_mtu6,
+            dns_servers: self
+                .existing_dns_servers
+                .iter()
+                .map(|addr| addr.0)
+                .collect(),
+        }
+    }
+}
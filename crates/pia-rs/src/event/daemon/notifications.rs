@@ -0,0 +1,233 @@
+use std::fmt;
+
+use super::{ConnectionState, DaemonState, UnixTime};
+
+/// How serious a [`Notification`] is, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A condition reported by the daemon that clients should show to the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub severity: Severity,
+    /// When the condition last occurred, for conditions the daemon timestamps. Clients can use
+    /// this to show a dismissed notification again if it happens again.
+    pub timestamp: Option<UnixTime>,
+}
+
+/// The kinds of notifications, mirroring `ClientNotifications.qml` in the official client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationKind {
+    /// Authentication failed in the OpenVPN connection. This doesn't necessarily mean the
+    /// credentials are wrong.
+    AuthFailed,
+    /// The connection was lost and the daemon is trying to reconnect.
+    ConnectionLost,
+    /// The configured proxy couldn't be resolved.
+    ProxyUnreachable,
+    /// Internet access is blocked by the killswitch while the VPN is disconnected.
+    KillswitchActive,
+    /// DNS couldn't be configured (Linux).
+    DnsConfigFailed,
+    /// The Handshake resolver keeps failing to launch.
+    HnsdFailing,
+    /// The Handshake resolver is running but can't sync.
+    HnsdSyncFailure,
+    /// The VPN seems to be connected, but the connection test failed.
+    ConnectionProblem,
+    /// The WinTUN driver is missing (Windows).
+    WintunMissing,
+    /// The TAP adapter is missing (Windows).
+    TapAdapterMissing,
+    /// A new version is available.
+    UpdateAvailable { version: String },
+    /// Downloading an update failed.
+    UpdateDownloadFailed { version: String },
+    /// Newer versions exist, but they don't support this OS version.
+    OsUnsupported,
+    /// A dedicated IP will expire soon.
+    DedicatedIpExpiring { days_remaining: i32 },
+    /// A dedicated IP's address has changed.
+    DedicatedIpChanged,
+    /// The last client exited unexpectedly.
+    InvalidClientExit,
+    /// Testing overrides couldn't be loaded.
+    OverridesFailed { overrides: Vec<String> },
+    /// Testing overrides are active.
+    OverridesActive { overrides: Vec<String> },
+}
+
+/// A short message for the user, e.g. `Connection lost, reconnecting...`.
+impl fmt::Display for NotificationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AuthFailed => f.write_str("Authentication failed"),
+            Self::ConnectionLost => f.write_str("Connection lost, reconnecting..."),
+            Self::ProxyUnreachable => f.write_str("Proxy unreachable, retrying..."),
+            Self::KillswitchActive => f.write_str("Killswitch is blocking internet access"),
+            Self::DnsConfigFailed => f.write_str("Couldn't configure DNS"),
+            Self::HnsdFailing => f.write_str("Handshake resolver is failing to start"),
+            Self::HnsdSyncFailure => f.write_str("Handshake resolver can't sync"),
+            Self::ConnectionProblem => f.write_str("The VPN connection doesn't seem to be working"),
+            Self::WintunMissing => f.write_str("The WinTUN driver isn't installed"),
+            Self::TapAdapterMissing => f.write_str("The TAP adapter isn't installed"),
+            Self::UpdateAvailable { version } => write!(f, "Version {version} is available"),
+            Self::UpdateDownloadFailed { version } => {
+                write!(f, "Downloading version {version} failed")
+            }
+            Self::OsUnsupported => f.write_str("Updates no longer support this OS version"),
+            Self::DedicatedIpExpiring { days_remaining: 1 } => {
+                f.write_str("A dedicated IP expires in 1 day")
+            }
+            Self::DedicatedIpExpiring { days_remaining } => {
+                write!(f, "A dedicated IP expires in {days_remaining} days")
+            }
+            Self::DedicatedIpChanged => f.write_str("A dedicated IP's address has changed"),
+            Self::InvalidClientExit => f.write_str("The client exited unexpectedly"),
+            Self::OverridesFailed { overrides } => {
+                write!(f, "Couldn't load overrides: {}", overrides.join(", "))
+            }
+            Self::OverridesActive { overrides } => {
+                write!(f, "Overrides are active: {}", overrides.join(", "))
+            }
+        }
+    }
+}
+
+impl DaemonState {
+    /// The notifications that currently apply, in roughly the order the official client shows
+    /// them.
+    pub fn active_notifications(&self) -> Vec<Notification> {
+        let mut notifications = Vec::new();
+        let mut push = |kind, severity, timestamp| {
+            notifications.push(Notification {
+                kind,
+                severity,
+                timestamp,
+            })
+        };
+        let timestamped = [
+            (
+                self.open_vpn_auth_failed,
+                NotificationKind::AuthFailed,
+                Severity::Error,
+            ),
+            (
+                self.connection_lost,
+                NotificationKind::ConnectionLost,
+                Severity::Error,
+            ),
+            (
+                self.proxy_unreachable,
+                NotificationKind::ProxyUnreachable,
+                Severity::Error,
+            ),
+            (
+                self.dns_config_failed,
+                NotificationKind::DnsConfigFailed,
+                Severity::Error,
+            ),
+            (
+                self.hnsd_failing,
+                NotificationKind::HnsdFailing,
+                Severity::Warning,
+            ),
+            (
+                self.hnsd_sync_failure,
+                NotificationKind::HnsdSyncFailure,
+                Severity::Warning,
+            ),
+        ];
+        for (time, kind, severity) in timestamped {
+            if let Some(time) = time.0 {
+                push(kind, severity, Some(time));
+            }
+        }
+
+        // the killswitch is expected to be active while connecting or connected
+        if self.killswitch_enabled
+            && !matches!(
+                self.connection_state,
+                ConnectionState::Connecting | ConnectionState::Connected
+            )
+        {
+            push(NotificationKind::KillswitchActive, Severity::Info, None);
+        }
+        if self.connection_problem {
+            push(NotificationKind::ConnectionProblem, Severity::Warning, None);
+        }
+        if self.wintun_missing {
+            push(NotificationKind::WintunMissing, Severity::Error, None);
+        }
+        if self.tap_adapter_missing {
+            push(NotificationKind::TapAdapterMissing, Severity::Error, None);
+        }
+
+        if let Some(time) = self.update_download_failure.0 {
+            push(
+                NotificationKind::UpdateDownloadFailed {
+                    version: self.update_version.clone(),
+                },
+                Severity::Warning,
+                Some(time),
+            );
+        } else if !self.available_version.is_empty() {
+            push(
+                NotificationKind::UpdateAvailable {
+                    version: self.available_version.clone(),
+                },
+                Severity::Info,
+                None,
+            );
+        }
+        if self.os_unsupported {
+            push(NotificationKind::OsUnsupported, Severity::Warning, None);
+        }
+
+        if let Some(time) = self.dedicated_ip_expiring.0 {
+            push(
+                NotificationKind::DedicatedIpExpiring {
+                    days_remaining: self.dedicated_ip_days_remaining,
+                },
+                Severity::Warning,
+                Some(time),
+            );
+        }
+        if let Some(time) = self.dedicated_ip_changed.0 {
+            push(
+                NotificationKind::DedicatedIpChanged,
+                Severity::Info,
+                Some(time),
+            );
+        }
+        if self.invalid_client_exit {
+            push(NotificationKind::InvalidClientExit, Severity::Warning, None);
+        }
+
+        if !self.overrides_failed.is_empty() {
+            push(
+                NotificationKind::OverridesFailed {
+                    overrides: self.overrides_failed.clone(),
+                },
+                Severity::Warning,
+                None,
+            );
+        }
+        if !self.overrides_active.is_empty() {
+            push(
+                NotificationKind::OverridesActive {
+                    overrides: self.overrides_active.clone(),
+                },
+                Severity::Info,
+                None,
+            );
+        }
+
+        notifications
+    }
+}
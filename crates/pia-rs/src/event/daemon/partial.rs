@@ -0,0 +1,188 @@
+use std::fmt;
+
+use serde::{Deserialize, Deserializer};
+
+/// Defines a struct along with a "partial" version of it where every field is optional.
+///
+/// The daemon only sends the properties that changed in `data` events, and only the given
+/// properties are changed by `applySettings`, so most structs need both versions. Fields that are
+/// unset in the partial struct aren't serialized. Fields that are themselves `Option`s become
+/// `Option<Option<T>>`, where `Some(None)` is an explicit `null`.
+///
+/// The full struct gets an `apply` method to update it from a partial one, and a partial struct
+/// with every field set can be converted into a full one with `TryFrom`. Partial structs can be
+/// combined with `merge` until `missing_field` returns `None`. `FIELDS` and the names
+/// returned by `apply` are the Rust field names.
+///
+/// A field can be given a default with `= expr` after its type. The conversion uses it if the
+/// field is missing, for fields the daemon might not send.
+///
+/// ```ignore
+/// partial_struct! {
+///     /// Docs for the full struct.
+///     #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+///     #[serde(rename_all = "camelCase")]
+///     pub struct Full => PartialFull {
+///         pub field: u32,
+///         pub optional_field: Vec<String> = Vec::new(),
+///     }
+/// }
+/// ```
+macro_rules! partial_struct {
+    (@take $partial:ident . $field:ident) => {
+        $partial
+            .$field
+            .ok_or(crate::event::daemon::MissingField(stringify!($field)))?
+    };
+    (@take $partial:ident . $field:ident = $default:expr) => {
+        $partial.$field.unwrap_or_else(|| $default)
+    };
+    (@required $partial:ident . $field:ident) => {
+        if $partial.$field.is_none() {
+            return Some(crate::event::daemon::MissingField(stringify!($field)));
+        }
+    };
+    (@required $partial:ident . $field:ident = $default:expr) => {};
+    (
+        $(#[doc = $doc:literal])*
+        #[derive($($derive:path),* $(,)?)]
+        $(#[serde($($serde:tt)*)])?
+        pub struct $name:ident => $partial:ident {
+            $(
+                $(#[$field_attr:meta])*
+                pub $field:ident: $ty:ty $(= $default:expr)?,
+            )*
+        }
+    ) => {
+        $(#[doc = $doc])*
+        #[derive($($derive),*)]
+        $(#[serde($($serde)*)])?
+        pub struct $name {
+            $(
+                $(#[$field_attr])*
+                pub $field: $ty,
+            )*
+        }
+
+        #[doc = concat!("A [`", stringify!($name), "`] with only some of its fields set.")]
+        #[derive($($derive,)* Default)]
+        $(#[serde($($serde)*)])?
+        pub struct $partial {
+            $(
+                $(#[$field_attr])*
+                #[serde(
+                    default,
+                    skip_serializing_if = "Option::is_none",
+                    deserialize_with = "crate::event::daemon::partial::deserialize_some"
+                )]
+                pub $field: Option<$ty>,
+            )*
+        }
+
+        impl $name {
+            /// Names of all the fields.
+            pub const FIELDS: &'static [&'static str] = &[$(stringify!($field)),*];
+
+            /// Overwrites the fields that are set in `partial`, returning the names of the ones
+            /// whose values changed.
+            pub fn apply(&mut self, partial: $partial) -> Vec<&'static str> {
+                let mut changed = Vec::new();
+                $(
+                    if let Some(value) = partial.$field {
+                        if self.$field != value {
+                            self.$field = value;
+                            changed.push(stringify!($field));
+                        }
+                    }
+                )*
+                changed
+            }
+        }
+
+        impl $partial {
+            /// Overwrites the fields that are set in `other`.
+            pub fn merge(&mut self, other: $partial) {
+                $(
+                    if other.$field.is_some() {
+                        self.$field = other.$field;
+                    }
+                )*
+            }
+
+            /// The first field that needs to be set to convert this into a
+            #[doc = concat!("[`", stringify!($name), "`], if any.")]
+            pub fn missing_field(&self) -> Option<crate::event::daemon::MissingField> {
+                $(
+                    crate::event::daemon::partial::partial_struct!(
+                        @required self.$field $(= $default)?
+                    );
+                )*
+                None
+            }
+        }
+
+        impl crate::event::daemon::partial::PartialObject for $partial {
+            type Full = $name;
+
+            fn fields() -> &'static [&'static str] {
+                $name::FIELDS
+            }
+            fn merge(&mut self, other: Self) {
+                $partial::merge(self, other)
+            }
+            fn missing_field(&self) -> Option<crate::event::daemon::MissingField> {
+                $partial::missing_field(self)
+            }
+            fn apply_to(full: &mut $name, partial: Self) -> Vec<&'static str> {
+                full.apply(partial)
+            }
+        }
+
+        impl TryFrom<$partial> for $name {
+            type Error = crate::event::daemon::MissingField;
+
+            fn try_from(partial: $partial) -> Result<Self, Self::Error> {
+                Ok(Self {
+                    $(
+                        $field: crate::event::daemon::partial::partial_struct!(
+                            @take partial.$field $(= $default)?
+                        ),
+                    )*
+                })
+            }
+        }
+    };
+}
+pub(crate) use partial_struct;
+
+/// The methods of a partial struct, so that code like [`DaemonModel`](crate::DaemonModel) can work
+/// with any of them.
+pub(crate) trait PartialObject: Sized {
+    type Full: TryFrom<Self, Error = MissingField>;
+
+    fn fields() -> &'static [&'static str];
+    fn merge(&mut self, other: Self);
+    fn missing_field(&self) -> Option<MissingField>;
+    fn apply_to(full: &mut Self::Full, partial: Self) -> Vec<&'static str>;
+}
+
+/// Deserializes a field that's present as `Some`, so that an explicit `null` in a double `Option`
+/// comes out as `Some(None)` instead of `None`.
+pub(crate) fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Returned when converting a partial struct into a full one that's missing a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissingField(pub &'static str);
+
+impl fmt::Display for MissingField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "missing field `{}`", self.0)
+    }
+}
+impl std::error::Error for MissingField {}
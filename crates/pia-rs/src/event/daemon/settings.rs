@@ -0,0 +1,325 @@
+use std::path::{Path, PathBuf};
+
+use serde::{de, ser};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::ServerCode;
+
+use super::{
+    partial::partial_struct,
+    state::{AutomationRule, AutomationRuleCondition, ConnectionMethod, TransportProtocol},
+};
+
+partial_struct! {
+    /// The daemon's settings. These are changed with
+    /// [`ClientEvent::ApplySettings`](crate::event::client::ClientEvent::ApplySettings).
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    pub struct DaemonSettings => PartialSettings {
+        /// The region to connect to. The location that will actually be used is in
+        /// [`DaemonState::vpn_locations`](super::DaemonState::vpn_locations).
+        pub location: LocationChoice,
+        /// VPN protocol to connect with.
+        pub method: ConnectionMethod,
+        /// Transport protocol for OpenVPN.
+        pub protocol: TransportProtocol,
+        /// Remote port for OpenVPN over UDP. 0 uses the server's default port.
+        #[serde(rename = "remotePortUDP")]
+        pub remote_port_udp: u16,
+        /// Remote port for OpenVPN over TCP. 0 uses the server's default port.
+        #[serde(rename = "remotePortTCP")]
+        pub remote_port_tcp: u16,
+        /// Local port for OpenVPN. 0 picks one automatically.
+        pub local_port: u16,
+        /// Whether to try other transports and ports when the chosen one doesn't work.
+        pub automatic_transport: bool,
+        /// Data encryption cipher for OpenVPN.
+        pub cipher: OpenVPNCipher,
+        /// Tunnel MTU. `-1` picks automatically, `0` uses the largest packets possible, and any
+        /// other value is used as-is.
+        pub mtu: i32,
+        /// DNS servers to use while connected.
+        #[serde(rename = "overrideDNS")]
+        pub override_dns: DNSSetting,
+        /// When to block Internet access outside of the VPN.
+        pub killswitch: KillSwitch,
+        /// Whether to allow access to devices on the local network while connected.
+        #[serde(rename = "allowLAN")]
+        pub allow_lan: bool,
+        /// Whether to block IPv6 traffic while connected, since the VPN only tunnels IPv4.
+        #[serde(rename = "blockIPv6")]
+        pub block_ipv6: bool,
+        /// Whether to request a forwarded port after connecting. The port is reported in
+        /// [`DaemonState::forwarded_port`](super::DaemonState::forwarded_port).
+        pub port_forward: bool,
+        /// Whether to use PIA MACE (named MACI internally) to block ads and trackers.
+        #[serde(rename = "enableMACI")]
+        pub enable_maci: bool,
+        /// Whether to use the kernel implementation of WireGuard when it's available (Linux only).
+        pub wireguard_use_kernel: bool,
+        /// Whether to connect through a proxy. This is only supported with OpenVPN.
+        pub proxy_enabled: bool,
+        /// Which proxy to use when [`proxy_enabled`](Self::proxy_enabled) is set.
+        pub proxy_type: ProxyType,
+        /// The proxy to use for [`ProxyType::Custom`].
+        pub proxy_custom: CustomProxy,
+        /// The region to use for [`ProxyType::Shadowsocks`].
+        pub proxy_shadowsocks_location: LocationChoice,
+        /// Whether traffic routed from other devices (e.g. VMs or containers) goes through the
+        /// VPN. Linux only.
+        #[serde(rename = "routedPacketsOnVPN")]
+        pub routed_packets_on_vpn: bool,
+        /// Whether split tunnel rules are applied. If split tunnel isn't available, the reasons
+        /// are in [`DaemonState::split_tunnel_support_errors`](super::DaemonState::split_tunnel_support_errors).
+        pub split_tunnel_enabled: bool,
+        /// Rules for which apps use the VPN.
+        pub split_tunnel_rules: Vec<SplitTunnelRule> = Vec::new(),
+        /// Subnets that bypass the VPN.
+        pub bypass_subnets: Vec<SplitTunnelSubnetRule> = Vec::new(),
+        /// Whether apps without a rule use the VPN ("All Other Apps" in the official client).
+        pub default_route: bool = true,
+        /// The "Name Servers" rule: whether DNS follows the app rules, so apps that bypass the
+        /// VPN use the existing DNS servers. If unset, all DNS goes through the VPN.
+        #[serde(rename = "splitTunnelDNS")]
+        pub split_tunnel_dns: bool = true,
+        /// Whether automation rules are applied. If they aren't available, the reasons are in
+        /// [`DaemonState::automation_support_errors`](super::DaemonState::automation_support_errors).
+        pub automation_enabled: bool = false,
+        /// Automation rules. A rule for a specific Wi-Fi network takes priority over a rule for a
+        /// type of network.
+        pub automation_rules: Vec<AutomationRule> = Vec::new(),
+        /// Whether the daemon keeps running after the client exits.
+        pub persist_daemon: bool,
+        /// Whether to offer beta releases as updates.
+        pub offer_beta_updates: bool,
+        /// Logging filters, or `None` if debug logging is disabled.
+        pub debug_logging: Option<Vec<String>>,
+        /// The last version of the client that ran; used to migrate settings.
+        pub last_used_version: String,
+    }
+}
+
+/// Which region to connect to. The daemon represents this as either `"auto"` or a region ID.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LocationChoice {
+    /// Let the daemon pick, usually the region with the lowest latency.
+    Auto,
+    Server(ServerCode),
+}
+
+impl From<ServerCode> for LocationChoice {
+    fn from(code: ServerCode) -> Self {
+        Self::Server(code)
+    }
+}
+
+impl ser::Serialize for LocationChoice {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Self::Auto => "auto".serialize(serializer),
+            Self::Server(code) => code.serialize(serializer),
+        }
+    }
+}
+impl<'de> de::Deserialize<'de> for LocationChoice {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(if s == "auto" {
+            Self::Auto
+        } else {
+            Self::Server(s.into())
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpenVPNCipher {
+    AES128GCM,
+    AES256GCM,
+    /// A cipher this crate doesn't know about, e.g. one added in a newer daemon.
+    Other(String),
+}
+
+impl OpenVPNCipher {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::AES128GCM => "AES-128-GCM",
+            Self::AES256GCM => "AES-256-GCM",
+            Self::Other(cipher) => cipher,
+        }
+    }
+}
+
+impl ser::Serialize for OpenVPNCipher {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.as_str().serialize(serializer)
+    }
+}
+impl<'de> de::Deserialize<'de> for OpenVPNCipher {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(match s.as_str() {
+            "AES-128-GCM" => Self::AES128GCM,
+            "AES-256-GCM" => Self::AES256GCM,
+            _ => Self::Other(s),
+        })
+    }
+}
+
+/// Which DNS servers to use while connected. The daemon represents this as a name, `""` for
+/// [`Existing`](Self::Existing), or a list of servers for [`Custom`](Self::Custom).
+#[derive(Debug, Clone, PartialEq)]
+pub enum DNSSetting {
+    /// PIA's DNS servers.
+    PIA,
+    /// The Handshake resolver.
+    Handshake,
+    /// A resolver built into the daemon.
+    Local,
+    /// Keep using the DNS servers from before connecting.
+    Existing,
+    Custom(Vec<String>),
+    /// A value this crate doesn't know about, e.g. from a newer daemon.
+    Other(serde_json::Value),
+}
+
+impl ser::Serialize for DNSSetting {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Self::PIA => "pia".serialize(serializer),
+            Self::Handshake => "handshake".serialize(serializer),
+            Self::Local => "local".serialize(serializer),
+            Self::Existing => "".serialize(serializer),
+            Self::Custom(servers) => servers.serialize(serializer),
+            Self::Other(value) => value.serialize(serializer),
+        }
+    }
+}
+impl<'de> de::Deserialize<'de> for DNSSetting {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        Ok(match value.as_str() {
+            Some("pia") => Self::PIA,
+            Some("handshake") => Self::Handshake,
+            Some("local") => Self::Local,
+            Some("") => Self::Existing,
+            _ => match serde_json::from_value(value.clone()) {
+                Ok(servers) => Self::Custom(servers),
+                Err(_) => Self::Other(value),
+            },
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum KillSwitch {
+    /// Never block Internet access.
+    Off,
+    /// Block Internet access outside the VPN while the VPN is enabled.
+    Auto,
+    /// Always block Internet access outside the VPN, even while disconnected.
+    On,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyType {
+    Shadowsocks,
+    Custom,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomProxy {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+}
+
+/// Whether traffic matching a split tunnel rule goes around or through the VPN.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitTunnelMode {
+    /// Bypass the VPN.
+    #[serde(rename = "exclude")]
+    Bypass,
+    /// Only use the VPN, and block the traffic while disconnected.
+    #[serde(rename = "include")]
+    OnlyVpn,
+}
+
+/// A split tunnel rule for an app.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitTunnelRule {
+    /// Path to the app's executable (or its bundle on macOS).
+    pub path: PathBuf,
+    /// Where the path points if it's a link, e.g. a Windows shortcut. Empty otherwise.
+    #[serde(default)]
+    pub link_target: String,
+    pub mode: SplitTunnelMode,
+}
+
+impl SplitTunnelRule {
+    pub fn new(path: impl Into<PathBuf>, mode: SplitTunnelMode) -> Self {
+        Self {
+            path: path.into(),
+            link_target: String::new(),
+            mode,
+        }
+    }
+}
+
+/// A split tunnel rule for a subnet.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitTunnelSubnetRule {
+    /// The subnet in CIDR notation, e.g. `192.168.1.0/24`. IPv6 subnets are allowed.
+    pub subnet: String,
+    /// The official client only creates [`SplitTunnelMode::Bypass`] subnet rules.
+    pub mode: SplitTunnelMode,
+}
+
+impl SplitTunnelSubnetRule {
+    /// A rule that makes `subnet` bypass the VPN.
+    pub fn bypass(subnet: impl Into<String>) -> Self {
+        Self {
+            subnet: subnet.into(),
+            mode: SplitTunnelMode::Bypass,
+        }
+    }
+}
+
+impl DaemonSettings {
+    /// The rule for the app at `path`, if there is one.
+    pub fn split_tunnel_rule(&self, path: &Path) -> Option<&SplitTunnelRule> {
+        self.split_tunnel_rules
+            .iter()
+            .find(|rule| rule.path == path)
+    }
+    /// The rule with `condition`, if there is one.
+    pub fn automation_rule(&self, condition: &AutomationRuleCondition) -> Option<&AutomationRule> {
+        self.automation_rules
+            .iter()
+            .find(|rule| rule.condition == *condition)
+    }
+}
@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use super::{DaemonState, UnixTime};
+
+/// Where the daemon is in updating the app, as shown by the official client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateStatus {
+    UpToDate,
+    /// A new version can be downloaded with
+    /// [`Call::download_update`](crate::event::client::Call::download_update).
+    Available(String),
+    /// The installer is being downloaded.
+    Downloading {
+        version: String,
+        percent: u8,
+    },
+    /// The installer has been downloaded and can be run.
+    Downloaded {
+        version: String,
+        installer: PathBuf,
+    },
+    /// Downloading the installer failed.
+    Failed {
+        version: String,
+        at: UnixTime,
+    },
+}
+
+impl DaemonState {
+    pub fn update_status(&self) -> UpdateStatus {
+        let version = self.update_version.clone();
+        if let Some(at) = self.update_download_failure.0 {
+            UpdateStatus::Failed { version, at }
+        } else if let Ok(percent) = u8::try_from(self.update_download_progress) {
+            UpdateStatus::Downloading { version, percent }
+        } else if !self.update_installer_path.is_empty() {
+            UpdateStatus::Downloaded {
+                version,
+                installer: self.update_installer_path.clone().into(),
+            }
+        } else if !self.available_version.is_empty() {
+            UpdateStatus::Available(self.available_version.clone())
+        } else {
+            UpdateStatus::UpToDate
+        }
+    }
+}
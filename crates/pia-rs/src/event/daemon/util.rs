@@ -10,7 +10,7 @@ use serde_derive::{Deserialize, Serialize};
 
 use crate::ServerCode;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(transparent)]
 pub struct UnixTime(pub u64);
 
@@ -27,8 +27,119 @@ impl Debug for UnixTime {
     }
 }
 
+/// A point in time on the system's monotonic clock, in milliseconds since some unspecified point
+/// (usually boot). The daemon uses these for durations that shouldn't be affected by changes to
+/// the wall clock; they don't count time spent asleep.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(transparent)]
+pub struct MonotonicTime(pub i64);
+
+impl MonotonicTime {
+    /// The current time on the same clock the daemon uses.
+    pub fn now() -> Self {
+        cfg_if::cfg_if! {
+            if #[cfg(target_os = "macos")] {
+                // what Qt uses through mach_absolute_time
+                const CLOCK: libc::clockid_t = libc::CLOCK_UPTIME_RAW;
+            } else {
+                const CLOCK: libc::clockid_t = libc::CLOCK_MONOTONIC;
+            }
+        }
+        let mut time = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        // SAFETY: `time` is a valid timespec to write to, and this clock exists on every
+        // supported platform, so this can't fail
+        unsafe { libc::clock_gettime(CLOCK, &mut time) };
+        // these aren't i64 on every platform
+        #[allow(clippy::unnecessary_cast)]
+        Self(time.tv_sec as i64 * 1000 + time.tv_nsec as i64 / 1_000_000)
+    }
+
+    /// How long after `earlier` this is, or zero if it's before `earlier`.
+    pub fn saturating_duration_since(self, earlier: Self) -> Duration {
+        Duration::from_millis(self.0.saturating_sub(earlier.0).try_into().unwrap_or(0))
+    }
+}
+
+/// Whether the VPN is snoozed, i.e. temporarily disconnected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SnoozeState {
+    Inactive,
+    /// The VPN is disconnecting to start snoozing.
+    Starting,
+    /// Snoozing until the given time. This can be in the past while the VPN reconnects after the
+    /// snooze ends.
+    Until(MonotonicTime),
+}
+
+impl SnoozeState {
+    /// How much longer the snooze lasts, if the VPN is snoozed.
+    pub fn remaining(self) -> Option<Duration> {
+        match self {
+            Self::Until(end) => Some(end.saturating_duration_since(MonotonicTime::now())),
+            Self::Inactive | Self::Starting => None,
+        }
+    }
+}
+
+impl ser::Serialize for SnoozeState {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Self::Inactive => -1,
+            Self::Starting => 0,
+            Self::Until(end) => end.0,
+        }
+        .serialize(serializer)
+    }
+}
+impl<'de> de::Deserialize<'de> for SnoozeState {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(match i64::deserialize(deserializer)? {
+            0 => Self::Starting,
+            end if end > 0 => Self::Until(MonotonicTime(end)),
+            _ => Self::Inactive,
+        })
+    }
+}
+
+/// A timestamp where 0 means there isn't one, used for conditions that may or may not apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OptionalUnixTime(pub Option<UnixTime>);
+
+impl ser::Serialize for OptionalUnixTime {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.map_or(0, |time| time.0).serialize(serializer)
+    }
+}
+impl<'de> de::Deserialize<'de> for OptionalUnixTime {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        // some of these are signed on the daemon's side, but never negative
+        let time = i64::deserialize(deserializer)?;
+        Ok(Self(
+            u64::try_from(time)
+                .ok()
+                .filter(|&time| time != 0)
+                .map(UnixTime),
+        ))
+    }
+}
+
 /// PIA uses "" instead of null for nonexistent IP addresses. :(
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct OptionalIpv4Addr(pub Option<net::Ipv4Addr>);
 
 impl ser::Serialize for OptionalIpv4Addr {
@@ -36,10 +147,7 @@ impl ser::Serialize for OptionalIpv4Addr {
     where
         S: serde::Serializer,
     {
-        match self.0 {
-            Some(addr) => addr.serialize(serializer),
-            None => "".serialize(serializer),
-        }
+        serialize_optional_addr(&self.0, serializer)
     }
 }
 impl<'de> de::Deserialize<'de> for OptionalIpv4Addr {
@@ -47,18 +155,164 @@ impl<'de> de::Deserialize<'de> for OptionalIpv4Addr {
     where
         D: serde::Deserializer<'de>,
     {
-        let s = <std::borrow::Cow<'_, str>>::deserialize(deserializer)?;
-        Ok(if s.is_empty() {
-            Self(None)
-        } else {
-            Self(Some(
-                net::Ipv4Addr::from_str(&s).map_err(de::Error::custom)?,
-            ))
+        deserialize_optional_addr(deserializer).map(Self)
+    }
+}
+
+/// Like [`OptionalIpv4Addr`], but for addresses that can be either IPv4 or IPv6.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OptionalIpAddr(pub Option<net::IpAddr>);
+
+impl ser::Serialize for OptionalIpAddr {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serialize_optional_addr(&self.0, serializer)
+    }
+}
+impl<'de> de::Deserialize<'de> for OptionalIpAddr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserialize_optional_addr(deserializer).map(Self)
+    }
+}
+
+/// An IPv4 address that PIA sends as a number, e.g. `3232235777` for `192.168.1.1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NumericIpv4Addr(pub net::Ipv4Addr);
+
+impl ser::Serialize for NumericIpv4Addr {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        u32::from(self.0).serialize(serializer)
+    }
+}
+impl<'de> de::Deserialize<'de> for NumericIpv4Addr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        u32::deserialize(deserializer).map(|addr| Self(addr.into()))
+    }
+}
+
+/// The prefix length of an IPv4 network, e.g. `24` for a `/24`. PIA sends `0` when there isn't
+/// one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OptionalIpv4Prefix(pub Option<u8>);
+
+impl ser::Serialize for OptionalIpv4Prefix {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        u32::from(self.0.unwrap_or(0)).serialize(serializer)
+    }
+}
+impl<'de> de::Deserialize<'de> for OptionalIpv4Prefix {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match u32::deserialize(deserializer)? {
+            0 => Ok(Self(None)),
+            // fits in a u8 because it's at most 32
+            len @ 1..=32 => Ok(Self(Some(len as u8))),
+            len => Err(de::Error::invalid_value(
+                de::Unexpected::Unsigned(len.into()),
+                &"a prefix length of at most 32",
+            )),
+        }
+    }
+}
+
+fn serialize_optional_addr<T, S>(addr: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: ser::Serialize,
+    S: serde::Serializer,
+{
+    match addr {
+        Some(addr) => addr.serialize(serializer),
+        None => ser::Serialize::serialize("", serializer),
+    }
+}
+fn deserialize_optional_addr<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: FromStr<Err = net::AddrParseError>,
+    D: serde::Deserializer<'de>,
+{
+    let s = <std::borrow::Cow<'_, str> as de::Deserialize>::deserialize(deserializer)?;
+    if s.is_empty() {
+        return Ok(None);
+    }
+    // link-local IPv6 addresses come with a zone, e.g. `fe80::1%eth0`, which `Ipv6Addr` can't hold
+    let addr = s.split_once('%').map_or(&*s, |(addr, _zone)| addr);
+    T::from_str(addr).map(Some).map_err(de::Error::custom)
+}
+
+/// The state of port forwarding, or the forwarded port. PIA sends this as a number where positive
+/// values are ports and the rest are `PortForwardState` values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PortForward {
+    Port(u16),
+    /// Port forwarding isn't enabled, or the VPN isn't connected.
+    Inactive,
+    /// Requesting a port.
+    Attempting,
+    /// Requesting a port failed.
+    Failed,
+    /// The region doesn't support port forwarding.
+    Unavailable,
+    /// A value this crate doesn't know about, e.g. a state added in a newer daemon.
+    Other(i32),
+}
+
+impl PortForward {
+    pub fn port(self) -> Option<u16> {
+        match self {
+            Self::Port(port) => Some(port),
+            _ => None,
+        }
+    }
+}
+
+impl ser::Serialize for PortForward {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let value: i32 = match self {
+            Self::Port(port) => (*port).into(),
+            Self::Inactive => 0,
+            Self::Attempting => -1,
+            Self::Failed => -2,
+            Self::Unavailable => -3,
+            Self::Other(value) => *value,
+        };
+        value.serialize(serializer)
+    }
+}
+impl<'de> de::Deserialize<'de> for PortForward {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(match i32::deserialize(deserializer)? {
+            0 => Self::Inactive,
+            -1 => Self::Attempting,
+            -2 => Self::Failed,
+            -3 => Self::Unavailable,
+            value => u16::try_from(value).map_or(Self::Other(value), Self::Port),
         })
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct Location {
     pub auto_safe: bool,
@@ -70,3 +324,34 @@ pub struct Location {
     pub offline: bool,
     pub port_forward: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn port_forward_round_trip() {
+        for (json, value) in [
+            ("0", PortForward::Inactive),
+            ("-1", PortForward::Attempting),
+            ("-2", PortForward::Failed),
+            ("-3", PortForward::Unavailable),
+            ("46123", PortForward::Port(46123)),
+            ("-4", PortForward::Other(-4)),
+            ("70000", PortForward::Other(70000)),
+        ] {
+            assert_eq!(serde_json::from_str::<PortForward>(json).unwrap(), value);
+            assert_eq!(serde_json::to_string(&value).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn optional_ip_addr_zone() {
+        let addr = serde_json::from_str::<OptionalIpAddr>(r#""fe80::1%eth0""#).unwrap();
+        assert_eq!(addr.0, Some("fe80::1".parse().unwrap()));
+        assert_eq!(
+            serde_json::from_str::<OptionalIpAddr>(r#""""#).unwrap(),
+            OptionalIpAddr(None)
+        );
+    }
+}
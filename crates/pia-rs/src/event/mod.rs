@@ -1,13 +1,38 @@
+use std::fmt;
+
 use serde_derive::{Deserialize, Serialize};
 
 pub mod client;
 pub mod daemon;
 
+/// Identifies a request so that its response can be matched up with it.
+pub type RequestId = u64;
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct JSONRPCMessage<Inner> {
     #[serde(rename = "jsonrpc")]
     pub jsonrpc_version: String,
 
+    /// Set for requests, which the other side will reply to; unset for notifications.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<RequestId>,
+
     #[serde(flatten)]
     pub event: Box<Inner>,
 }
+
+/// A JSON-RPC 2.0 error object, returned by the daemon when a request fails.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "daemon returned error {}: {}", self.code, self.message)
+    }
+}
+impl std::error::Error for RpcError {}
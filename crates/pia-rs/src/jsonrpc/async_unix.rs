@@ -0,0 +1,109 @@
+use std::{
+    future, io,
+    path::Path,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::UnixStream,
+    time::Instant,
+};
+
+use super::Session;
+use crate::Error;
+
+/// Connects to the daemon socket at `path`. See [`super::unix::create`] for how `timeout` works.
+pub async fn connect(
+    path: &Path,
+    timeout: Option<Duration>,
+) -> io::Result<AsyncDaemonJSONRPCConnection> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        match UnixStream::connect(path).await {
+            Ok(stream) => return Ok(AsyncDaemonJSONRPCConnection::new(stream)),
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+                ) && deadline.is_some() =>
+            {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("timed out connecting to {}: {err}", path.display()),
+                    ));
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AsyncDaemonJSONRPCConnection {
+    stream: UnixStream,
+
+    session: Session,
+}
+
+impl AsyncDaemonJSONRPCConnection {
+    fn new(stream: UnixStream) -> Self {
+        Self {
+            stream,
+            session: Session::new(),
+        }
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    /// Polls a message from the connection. Returns `None` once the daemon closes the connection.
+    pub fn poll_message(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Option<Vec<u8>>>> {
+        loop {
+            let msg = self.session.next_message();
+            // acks don't need to go out right away; whatever doesn't fit now is sent next time
+            if let Poll::Ready(Err(err)) = self.poll_flush_out(cx) {
+                return Poll::Ready(Err(err));
+            }
+            if let Some(msg) = msg {
+                return Poll::Ready(Ok(Some(msg)));
+            }
+
+            let mut buf = [0; 8192];
+            let mut buf = ReadBuf::new(&mut buf);
+            ready!(Pin::new(&mut self.stream).poll_read(cx, &mut buf))?;
+            if buf.filled().is_empty() {
+                return Poll::Ready(Ok(None));
+            }
+            self.session.extend(buf.filled());
+        }
+    }
+
+    /// Sends a message, or queues it if too many messages are awaiting acknowledgement.
+    ///
+    /// Queued messages are sent as acks come in during [`poll_message`](Self::poll_message).
+    pub async fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.session.write(bytes)?;
+        future::poll_fn(|cx| self.poll_flush_out(cx)).await?;
+        Ok(())
+    }
+
+    fn poll_flush_out(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.session.output().is_empty() {
+            let written = ready!(Pin::new(&mut self.stream).poll_write(cx, self.session.output()))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero)));
+            }
+            self.session.consume_output(written);
+        }
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+}
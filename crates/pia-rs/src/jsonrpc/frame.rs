@@ -0,0 +1,254 @@
+//! Framing for PIA's IPC layer.
+//!
+//! Each frame consists of a 12-byte header followed by the message payload:
+//!
+//! | Offset | Size | Contents                                             |
+//! |--------|------|------------------------------------------------------|
+//! | 0      | 4    | [`PIA_LOCAL_SOCKET_MAGIC`]                           |
+//! | 4      | 2    | Low byte of the sequence number, shifted left by 4   |
+//! | 6      | 2    | High byte of the sequence number, shifted left by 4  |
+//! | 8      | 4    | Payload length (little endian); 0 for an ack         |
+//!
+//! The sequence number is encoded this way so that the header can never contain the magic number
+//! anywhere other than at its start. Payloads are UTF-8 JSON, which never contains `0xFF`, so the
+//! magic number can't appear inside a payload either. This is what allows the reader to
+//! resynchronize: if a frame is garbled or cut off (say, because the daemon restarted while
+//! writing it), the reader discards bytes until it finds the next magic number and picks up from
+//! there.
+
+use super::{PIA_LOCAL_SOCKET_MAGIC, VALID_MESSAGE_SIZES};
+
+const HEADER_LEN: usize = 12;
+
+/// Mask of the bits that may be set in each half of an encoded sequence number.
+const SEQ_HALF_MASK: u16 = 0x0FF0;
+
+/// Encodes a frame with the given sequence number and payload. An empty payload is an ack.
+pub fn encode_frame(seq_num: u16, bytes: &[u8]) -> Vec<u8> {
+    let [seq_low, seq_hi] = seq_num.to_le_bytes();
+
+    let mut frame = Vec::with_capacity(HEADER_LEN + bytes.len());
+    frame.extend_from_slice(&PIA_LOCAL_SOCKET_MAGIC);
+    frame.extend_from_slice(&((seq_low as u16) << 4).to_le_bytes());
+    frame.extend_from_slice(&((seq_hi as u16) << 4).to_le_bytes());
+    frame.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    frame.extend_from_slice(bytes);
+    frame
+}
+
+/// Incrementally splits a byte stream into frames, resynchronizing on garbage.
+///
+/// This doesn't do any IO itself; feed it bytes with [`extend`](Self::extend) and pull frames out
+/// with [`next_frame`](Self::next_frame).
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+    /// How many bytes of the current frame's payload are known not to contain the magic number.
+    checked: usize,
+    dropped_bytes: u64,
+}
+
+impl FrameDecoder {
+    /// Appends bytes read from the stream.
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Total number of bytes discarded while resynchronizing.
+    pub fn dropped_bytes(&self) -> u64 {
+        self.dropped_bytes
+    }
+
+    /// Whether a frame has been started but not yet completely received.
+    pub fn in_frame(&self) -> bool {
+        self.buf.starts_with(&PIA_LOCAL_SOCKET_MAGIC)
+    }
+
+    /// Returns the next complete frame as a sequence number and its payload, if there is one.
+    pub fn next_frame(&mut self) -> Option<(u16, Vec<u8>)> {
+        loop {
+            let Some(start) = find_magic(&self.buf) else {
+                // keep a trailing partial magic number around; the rest can't be part of a frame
+                let keep = (1..PIA_LOCAL_SOCKET_MAGIC.len())
+                    .rev()
+                    .find(|&len| self.buf.ends_with(&PIA_LOCAL_SOCKET_MAGIC[..len]))
+                    .unwrap_or(0);
+                self.discard(self.buf.len() - keep);
+                return None;
+            };
+            self.discard(start);
+
+            if self.buf.len() < HEADER_LEN {
+                return None;
+            }
+
+            let seq_low = u16::from_le_bytes([self.buf[4], self.buf[5]]);
+            let seq_high = u16::from_le_bytes([self.buf[6], self.buf[7]]);
+            let length = u32::from_le_bytes([self.buf[8], self.buf[9], self.buf[10], self.buf[11]]);
+
+            if seq_low & !SEQ_HALF_MASK != 0
+                || seq_high & !SEQ_HALF_MASK != 0
+                || (length != 0 && !VALID_MESSAGE_SIZES.contains(&length))
+            {
+                // not actually a header; skip past this magic number and look for another
+                self.discard(1);
+                continue;
+            }
+            let seq_num = (seq_low >> 4) | ((seq_high >> 4) << 8);
+            let length = length as usize;
+
+            // make sure the payload wasn't cut off by the start of another frame
+            let available = &self.buf[HEADER_LEN..self.buf.len().min(HEADER_LEN + length)];
+            let search_from = self
+                .checked
+                .saturating_sub(PIA_LOCAL_SOCKET_MAGIC.len() - 1);
+            if let Some(pos) = find_magic(&available[search_from..]) {
+                self.discard(HEADER_LEN + search_from + pos);
+                continue;
+            }
+            self.checked = available.len();
+
+            if available.len() < length {
+                return None;
+            }
+
+            let payload = self.buf[HEADER_LEN..HEADER_LEN + length].to_vec();
+            self.buf.drain(..HEADER_LEN + length);
+            self.checked = 0;
+            return Some((seq_num, payload));
+        }
+    }
+
+    fn discard(&mut self, len: usize) {
+        if len == 0 {
+            return;
+        }
+        self.buf.drain(..len);
+        self.checked = 0;
+        self.dropped_bytes += len as u64;
+    }
+}
+
+fn find_magic(bytes: &[u8]) -> Option<usize> {
+    bytes
+        .windows(PIA_LOCAL_SOCKET_MAGIC.len())
+        .position(|window| window == PIA_LOCAL_SOCKET_MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(decoder: &mut FrameDecoder) -> Vec<(u16, Vec<u8>)> {
+        std::iter::from_fn(|| decoder.next_frame()).collect()
+    }
+
+    /// A header with the given raw sequence halves and length.
+    fn header(seq_low: u16, seq_high: u16, length: u32) -> Vec<u8> {
+        let mut header = PIA_LOCAL_SOCKET_MAGIC.to_vec();
+        header.extend_from_slice(&seq_low.to_le_bytes());
+        header.extend_from_slice(&seq_high.to_le_bytes());
+        header.extend_from_slice(&length.to_le_bytes());
+        header
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut decoder = FrameDecoder::default();
+        decoder.extend(&encode_frame(0x1234, b"{\"a\":1}"));
+        decoder.extend(&encode_frame(0x1235, b""));
+        assert_eq!(
+            decode_all(&mut decoder),
+            [(0x1234, b"{\"a\":1}".to_vec()), (0x1235, Vec::new())]
+        );
+        assert_eq!(decoder.dropped_bytes(), 0);
+        assert!(!decoder.in_frame());
+    }
+
+    #[test]
+    fn byte_at_a_time() {
+        let mut decoder = FrameDecoder::default();
+        let frame = encode_frame(7, b"[1,2,3]");
+        let mut frames = Vec::new();
+        for byte in &frame {
+            decoder.extend(&[*byte]);
+            frames.extend(decoder.next_frame());
+        }
+        assert_eq!(frames, [(7, b"[1,2,3]".to_vec())]);
+        assert_eq!(decoder.dropped_bytes(), 0);
+    }
+
+    #[test]
+    fn leading_garbage() {
+        let mut decoder = FrameDecoder::default();
+        decoder.extend(b"garbage");
+        decoder.extend(&encode_frame(1, b"{}"));
+        assert_eq!(decode_all(&mut decoder), [(1, b"{}".to_vec())]);
+        assert_eq!(decoder.dropped_bytes(), 7);
+    }
+
+    #[test]
+    fn frame_cut_off_by_next_magic() {
+        let mut decoder = FrameDecoder::default();
+        let mut truncated = encode_frame(1, b"{\"truncated\":true}");
+        truncated.truncate(HEADER_LEN + 5);
+        decoder.extend(&truncated);
+        assert_eq!(decoder.next_frame(), None);
+        assert!(decoder.in_frame());
+
+        decoder.extend(&encode_frame(2, b"{}"));
+        assert_eq!(decode_all(&mut decoder), [(2, b"{}".to_vec())]);
+        assert_eq!(decoder.dropped_bytes(), truncated.len() as u64);
+    }
+
+    #[test]
+    fn bad_length() {
+        for length in [1, *VALID_MESSAGE_SIZES.end() + 1] {
+            let mut decoder = FrameDecoder::default();
+            let bad = header(0, 0, length);
+            decoder.extend(&bad);
+            decoder.extend(&encode_frame(3, b"{}"));
+            assert_eq!(decode_all(&mut decoder), [(3, b"{}".to_vec())]);
+            assert_eq!(decoder.dropped_bytes(), bad.len() as u64);
+        }
+    }
+
+    #[test]
+    fn bad_sequence_nibble() {
+        for (seq_low, seq_high) in [(0x0001, 0), (0, 0xF000)] {
+            let mut decoder = FrameDecoder::default();
+            let mut bad = header(seq_low, seq_high, 2);
+            bad.extend_from_slice(b"{}");
+            decoder.extend(&bad);
+            decoder.extend(&encode_frame(4, b"{}"));
+            assert_eq!(decode_all(&mut decoder), [(4, b"{}".to_vec())]);
+            assert_eq!(decoder.dropped_bytes(), bad.len() as u64);
+        }
+    }
+
+    #[test]
+    fn partial_magic_at_end() {
+        let frame = encode_frame(5, b"{}");
+        for split in 1..PIA_LOCAL_SOCKET_MAGIC.len() {
+            let mut decoder = FrameDecoder::default();
+            decoder.extend(b"junk");
+            decoder.extend(&frame[..split]);
+            assert_eq!(decoder.next_frame(), None);
+            // the garbage is gone, but the start of the magic number is kept
+            assert_eq!(decoder.dropped_bytes(), 4);
+
+            decoder.extend(&frame[split..]);
+            assert_eq!(decode_all(&mut decoder), [(5, b"{}".to_vec())]);
+            assert_eq!(decoder.dropped_bytes(), 4);
+        }
+    }
+
+    #[test]
+    fn garbage_without_magic_is_dropped() {
+        let mut decoder = FrameDecoder::default();
+        decoder.extend(b"no frames here");
+        assert_eq!(decoder.next_frame(), None);
+        assert_eq!(decoder.dropped_bytes(), 14);
+        assert!(!decoder.in_frame());
+    }
+}
@@ -5,19 +5,28 @@
 //! This module is a rough translation of its C++ code to Rust. Go check out the [explanation](https://github.com/pia-foss/desktop/blob/522751571ea7f6b1a9e3dd5cc4c70fc2fd136221/common/src/ipc.cpp#L33) in the PIA repo for more details.
 
 use std::{
-    io::{self, Read, Write},
-    sync::{
-        atomic::{AtomicBool, AtomicU16, AtomicU8, Ordering},
-        RwLock,
-    },
+    collections::VecDeque,
+    io::{self, BufRead, Write},
+    path::Path,
+    time::{Duration, Instant},
 };
 
+use crate::Error;
+
+mod frame;
+
 cfg_if::cfg_if! {
     if #[cfg(unix)] {
         pub mod unix;
         use unix::create;
+        pub use unix::DEFAULT_SOCKET_PATH;
         type PlatformDaemonConnectionReader = unix::UnixSocketDaemonConnectionReader;
         type PlatformDaemonConnectionWriter = unix::UnixSocketDaemonConnectionWriter;
+
+        #[cfg(feature = "tokio")]
+        mod async_unix;
+        #[cfg(feature = "tokio")]
+        pub use async_unix::{connect as connect_async, AsyncDaemonJSONRPCConnection};
     } else {
         compile_error!("platform not implemented D:");
     }
@@ -29,134 +38,247 @@ const PIA_LOCAL_SOCKET_MAGIC: [u8; 4] = 0xFFACCE56u32.to_be_bytes();
 // Valid message sizes. Copied from PIA source
 const VALID_MESSAGE_SIZES: std::ops::RangeInclusive<u32> = 2..=1024 * 1024;
 
-pub struct ConnectionInfo {
-    global: RwLock<GlobalConnectionInfo>,
+/// Maximum number of messages that can be in flight without being acknowledged by the daemon.
+/// Anything sent past this is held back until the daemon catches up.
+pub const MAX_UNACKED_MESSAGES: u16 = 32;
 
-    last_server_ack: AtomicU16,
-    last_send_seq: AtomicU16,
+/// Maximum number of messages that can be held back, waiting for acks or for the socket to accept
+/// them, before sends are rejected.
+pub const MAX_QUEUED_MESSAGES: usize = 64;
 
-    live: AtomicBool,
+pub fn connect(
+    path: &Path,
+    timeout: Option<Duration>,
+    nonblocking: bool,
+) -> io::Result<DaemonJSONRPCConnection> {
+    let (reader, writer) = create(path, timeout, nonblocking)?;
+    Ok(DaemonJSONRPCConnection::new(reader, writer, nonblocking))
 }
 
-pub static CONNECTION_INFO: ConnectionInfo = ConnectionInfo {
-    global: RwLock::new(GlobalConnectionInfo {
-        jsonrpc_version: None,
-    }),
+/// Connection state shared by every transport: framing, sequence numbers, acks and the send queue.
+///
+/// This doesn't do any IO itself. Bytes read from the daemon go in through
+/// [`extend`](Self::extend), and anything that needs to be written to the daemon (messages, acks)
+/// is put in an output buffer. The transport writes out as much of [`output`](Self::output) as it
+/// can and reports it with [`consume_output`](Self::consume_output); the rest stays buffered until
+/// the next time.
+#[derive(Debug, Default)]
+pub struct Session {
+    decoder: frame::FrameDecoder,
+    /// Messages that haven't been completely written yet. The first one may be partially written.
+    queue: VecDeque<Vec<u8>>,
+    out: Vec<u8>,
+    /// Where the first queued message's frame ends in `out`, once it's been put there.
+    front_end: Option<usize>,
 
-    last_server_ack: AtomicU16::new(0),
-    last_send_seq: AtomicU16::new(0),
-
-    live: AtomicBool::new(false),
-};
+    last_server_ack: u16,
+    last_send_seq: u16,
 
-struct GlobalConnectionInfo {
+    /// JSON-RPC version reported by the daemon, once it has sent something.
     jsonrpc_version: Option<String>,
 }
 
-#[derive(Debug)]
-pub enum TakeConnectionError {
-    Io(io::Error),
-    AlreadyTaken,
-}
-impl From<io::Error> for TakeConnectionError {
-    fn from(inner: io::Error) -> Self {
-        Self::Io(inner)
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
     }
-}
 
-pub fn take_connection() -> Result<DaemonJSONRPCConnection, TakeConnectionError> {
-    if CONNECTION_INFO.live.load(Ordering::Acquire) {
-        // connection still exists
-        return Err(TakeConnectionError::AlreadyTaken);
+    /// Appends bytes read from the daemon.
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.decoder.extend(bytes);
     }
 
-    let (reader, writer) = create()?;
-
-    CONNECTION_INFO.last_server_ack.store(0, Ordering::Release);
-    CONNECTION_INFO.last_send_seq.store(0, Ordering::Release);
-    Ok(DaemonJSONRPCConnection::new(reader, writer))
-}
-
-#[derive(Debug)]
-pub struct DaemonJSONRPCConnection {
-    reader: PlatformDaemonConnectionReader,
-    writer: PlatformDaemonConnectionWriter,
-}
-
-impl DaemonJSONRPCConnection {
-    fn new(reader: PlatformDaemonConnectionReader, writer: PlatformDaemonConnectionWriter) -> Self {
-        CONNECTION_INFO.live.store(true, Ordering::Release);
-        Self { reader, writer }
+    /// Whether a frame has been partially received.
+    pub fn in_frame(&self) -> bool {
+        self.decoder.in_frame()
     }
 
-    pub fn poll(&mut self) -> io::Result<Vec<u8>> {
-        loop {
-            let (seq_num, msg) = self.poll_raw()?;
+    /// Returns the next message received from the daemon, if a complete one has been received.
+    ///
+    /// Acks for received messages, and queued messages that the daemon now has room for, are
+    /// added to the output.
+    pub fn next_message(&mut self) -> Option<Vec<u8>> {
+        while let Some((seq_num, msg)) = self.decoder.next_frame() {
             if msg.is_empty() {
-                // message is an acknowledgement message; ignore
-                CONNECTION_INFO
-                    .last_server_ack
-                    .store(seq_num, Ordering::Release);
+                // message is an acknowledgement message; the daemon might have room for more now
+                self.last_server_ack = seq_num;
+                self.fill_output();
             } else {
-                self.write_raw(seq_num, &[])?;
-                break Ok(msg);
+                self.out.extend(frame::encode_frame(seq_num, &[]));
+                return Some(msg);
             }
         }
+        None
+    }
+
+    /// Bytes waiting to be written to the daemon.
+    pub fn output(&self) -> &[u8] {
+        &self.out
     }
 
-    /// Polls a message from the connection and returns the sequence number and its contents.
-    fn poll_raw(&mut self) -> io::Result<(u16, Vec<u8>)> {
-        // message header
-        let mut header_buf = [0; 12];
-        self.read_exact(&mut header_buf, false)?;
-        let header_buf: [u32; 3] = bytemuck::cast(header_buf);
-        if header_buf[0].to_le_bytes() != PIA_LOCAL_SOCKET_MAGIC {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!(
-                    "expected {PIA_LOCAL_SOCKET_MAGIC:?} as header magic number, got {:?}",
-                    header_buf[0].to_le_bytes()
-                ),
-            ));
+    /// Removes `len` bytes that have been written from the start of the output.
+    pub fn consume_output(&mut self, len: usize) {
+        self.out.drain(..len);
+        let Some(end) = self.front_end else {
+            return;
+        };
+        if len < end {
+            self.front_end = Some(end - len);
+            return;
         }
-        let seq_shorts: [u16; 2] = bytemuck::cast(header_buf[1]);
-        let seq_low = (seq_shorts[0].to_le() >> 4) as u8;
-        let seq_high = (seq_shorts[1].to_le() >> 4) as u8;
-        let seq_num = seq_low as u16 | (seq_high as u16) << 8;
+        // the message is completely written, so it's now in flight
+        self.front_end = None;
+        self.queue.pop_front();
+        self.last_send_seq = self.last_send_seq.wrapping_add(1);
+        self.fill_output();
+    }
 
-        let length = header_buf[2].to_le();
+    /// Total number of bytes discarded so far while resynchronizing after bad frames.
+    pub fn dropped_bytes(&self) -> u64 {
+        self.decoder.dropped_bytes()
+    }
 
-        if length == 0 {
-            return Ok((seq_num, vec![]));
+    /// The JSON-RPC version the daemon is speaking, if it has sent any messages yet.
+    pub fn jsonrpc_version(&self) -> Option<&str> {
+        self.jsonrpc_version.as_deref()
+    }
+    pub(crate) fn set_jsonrpc_version(&mut self, version: &str) {
+        if self.jsonrpc_version.as_deref() != Some(version) {
+            self.jsonrpc_version = Some(version.to_owned());
         }
+    }
+
+    /// Number of messages written that the daemon hasn't acknowledged yet.
+    pub fn unacknowledged(&self) -> u16 {
+        self.last_send_seq.wrapping_sub(self.last_server_ack)
+    }
 
-        if !VALID_MESSAGE_SIZES.contains(&length) {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("invalid length {:?} in header", length),
-            ));
+    /// Number of messages that haven't been completely written yet, because the daemon needs to
+    /// catch up or the socket is full.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Adds a message to the output, or queues it if too many messages are awaiting
+    /// acknowledgement.
+    ///
+    /// Queued messages are added as acks come in during [`next_message`](Self::next_message) and
+    /// as earlier messages are written.
+    pub fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        if !VALID_MESSAGE_SIZES.contains(&(bytes.len() as u32)) {
+            return Err(Error::Framing { len: bytes.len() });
+        }
+        if self.queue.len() >= MAX_QUEUED_MESSAGES {
+            return Err(Error::Backpressure {
+                unacknowledged: self.unacknowledged(),
+                queued: self.queue.len(),
+            });
         }
+        self.queue.push_back(bytes.to_vec());
+        self.fill_output();
+        Ok(())
+    }
 
-        let mut buf = vec![0; length as usize];
-        self.read_exact(&mut buf, true)?;
-        Ok((seq_num, buf))
+    /// Adds the first queued message to the output if it isn't there yet and the daemon has room
+    /// for it. Only one message is in the output at a time, so that messages stay queued (and
+    /// count towards [`MAX_QUEUED_MESSAGES`]) while the socket is full.
+    fn fill_output(&mut self) {
+        if self.front_end.is_some() || self.unacknowledged() >= MAX_UNACKED_MESSAGES {
+            return;
+        }
+        let Some(bytes) = self.queue.front() else {
+            return;
+        };
+        let seq_num = self.last_send_seq.wrapping_add(1);
+        self.out.extend(frame::encode_frame(seq_num, bytes));
+        self.front_end = Some(self.out.len());
     }
+}
 
-    fn read_exact(&mut self, mut buf: &mut [u8], block: bool) -> io::Result<()> {
-        let mut is_first_loop: bool = true;
+#[derive(Debug)]
+pub struct DaemonJSONRPCConnection {
+    reader: PlatformDaemonConnectionReader,
+    writer: PlatformDaemonConnectionWriter,
+    nonblocking: bool,
+
+    session: Session,
+}
+
+impl DaemonJSONRPCConnection {
+    fn new(
+        reader: PlatformDaemonConnectionReader,
+        writer: PlatformDaemonConnectionWriter,
+        nonblocking: bool,
+    ) -> Self {
+        Self {
+            reader,
+            writer,
+            nonblocking,
+            session: Session::new(),
+        }
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    /// Polls a message from the connection.
+    ///
+    /// Garbled or truncated frames are skipped; see [`Session::dropped_bytes`].
+    pub fn poll(&mut self) -> io::Result<Vec<u8>> {
+        self.poll_inner(None)
+    }
+
+    /// Waits for a message until `deadline`, even if the connection is nonblocking. Returns a
+    /// [`TimedOut`](io::ErrorKind::TimedOut) error if none arrives in time.
+    pub fn poll_until(&mut self, deadline: Instant) -> io::Result<Vec<u8>> {
+        self.reader.get_ref().set_nonblocking(false)?;
+        let res = self.poll_inner(Some(deadline));
+        let socket = self.reader.get_ref();
+        socket.set_read_timeout(None)?;
+        socket.set_write_timeout(None)?;
+        socket.set_nonblocking(self.nonblocking)?;
+        res
+    }
+
+    fn poll_inner(&mut self, deadline: Option<Instant>) -> io::Result<Vec<u8>> {
         loop {
-            let res = self.reader.read(buf);
-
-            match res {
-                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
-                Ok(read) => {
-                    buf = &mut buf[read..];
-                    if buf.is_empty() {
-                        break;
-                    }
+            if let Some(deadline) = deadline {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Err(io::Error::from(io::ErrorKind::TimedOut));
                 }
+                // a write timing out leaves the rest of the output for later, like `WouldBlock`
+                let socket = self.reader.get_ref();
+                socket.set_read_timeout(Some(remaining))?;
+                socket.set_write_timeout(Some(remaining))?;
+            }
+
+            let msg = self.session.next_message();
+            self.flush()?;
+            if let Some(msg) = msg {
+                return Ok(msg);
+            }
+
+            match self.reader.fill_buf() {
+                Ok([]) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+                Ok(buf) => {
+                    let read = buf.len();
+                    self.session.extend(buf);
+                    self.reader.consume(read);
+                }
+                // the read timed out; the deadline is checked above
+                Err(err)
+                    if deadline.is_some()
+                        && matches!(
+                            err.kind(),
+                            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                        ) => {}
                 Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
-                    if !block && is_first_loop {
+                    if !self.session.in_frame() {
                         return Err(err);
                     }
                     // other side is still writing; sleep for a bit
@@ -165,50 +287,78 @@ impl DaemonJSONRPCConnection {
                 Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
                 Err(err) => return Err(err),
             }
-            is_first_loop = false;
         }
-        Ok(())
     }
 
-    pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
-        self.write_raw(
-            CONNECTION_INFO
-                .last_send_seq
-                .fetch_add(1, Ordering::SeqCst)
-                .wrapping_add(1),
-            bytes,
-        )
-    }
-    fn write_raw(&mut self, seq_num: u16, bytes: &[u8]) -> io::Result<()> {
-        // can't do .into() :pensive:
-        const VALID_MESSAGE_SIZES_USIZE: std::ops::RangeInclusive<usize> =
-            *VALID_MESSAGE_SIZES.start() as usize..=*VALID_MESSAGE_SIZES.end() as usize;
-
-        assert!(
-            bytes.is_empty() || VALID_MESSAGE_SIZES_USIZE.contains(&bytes.len()),
-            "message is not an ack and its len {} is not in range of {:?}",
-            bytes.len(),
-            VALID_MESSAGE_SIZES_USIZE
-        );
-
-        // TODO: add checks for if the daemon is falling behind
-        let [seq_low, seq_hi] = seq_num.to_le_bytes();
-
-        self.writer.write_all(&PIA_LOCAL_SOCKET_MAGIC)?;
-        self.writer
-            .write_all(&((seq_low as u16) << 4).to_le_bytes())?;
-        self.writer
-            .write_all(&((seq_hi as u16) << 4).to_le_bytes())?;
-        self.writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
-        self.writer.write_all(bytes)?;
-        self.writer.flush()?;
+    /// Sends a message, or queues it if too many messages are awaiting acknowledgement.
+    ///
+    /// Queued messages are sent as acks come in during [`poll`](Self::poll). On a nonblocking
+    /// connection, whatever doesn't fit in the socket's buffer is also written during later calls
+    /// to `poll` or `write`.
+    pub fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.session.write(bytes)?;
+        Ok(self.flush()?)
+    }
 
+    /// Writes as much of the session's output as the socket accepts.
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.session.output().is_empty() {
+            match self.writer.write(self.session.output()) {
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::WriteZero)),
+                Ok(written) => self.session.consume_output(written),
+                // the daemon isn't reading fast enough; the rest is written next time
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(())
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
         Ok(())
     }
 }
 
-impl Drop for DaemonJSONRPCConnection {
-    fn drop(&mut self) {
-        CONNECTION_INFO.live.store(false, Ordering::Release);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partially_written_messages_are_kept() {
+        let mut session = Session::new();
+        session.write(b"{\"a\":1}").unwrap();
+        session.write(b"{\"b\":2}").unwrap();
+        // only the first message is in the output until it's written
+        let first = frame::encode_frame(1, b"{\"a\":1}");
+        assert_eq!(session.output(), first);
+        assert_eq!(session.queued(), 2);
+
+        session.consume_output(5);
+        assert_eq!(session.output(), &first[5..]);
+        assert_eq!(session.unacknowledged(), 0);
+
+        session.consume_output(first.len() - 5);
+        assert_eq!(session.unacknowledged(), 1);
+        assert_eq!(session.queued(), 1);
+        assert_eq!(session.output(), frame::encode_frame(2, b"{\"b\":2}"));
+    }
+
+    #[test]
+    fn backpressure_once_queue_is_full() {
+        let mut session = Session::new();
+        for _ in 0..MAX_QUEUED_MESSAGES {
+            session.write(b"{}").unwrap();
+        }
+        assert!(matches!(
+            session.write(b"{}"),
+            Err(Error::Backpressure {
+                unacknowledged: 0,
+                queued: MAX_QUEUED_MESSAGES,
+            })
+        ));
     }
 }
@@ -1,16 +1,55 @@
 use std::{
-    io::{self, BufReader, BufWriter},
+    io::{self, BufReader},
     os::unix::net::UnixStream,
+    path::Path,
+    time::{Duration, Instant},
 };
 
-pub fn create() -> io::Result<(
+pub const DEFAULT_SOCKET_PATH: &str = "/opt/piavpn/var/daemon.sock";
+
+/// Connects to the daemon socket at `path`.
+///
+/// With a `timeout`, connecting is retried until it elapses if the socket doesn't exist yet or
+/// nobody is listening on it, e.g. while the daemon is starting up.
+pub fn create(
+    path: &Path,
+    timeout: Option<Duration>,
+    nonblocking: bool,
+) -> io::Result<(
     UnixSocketDaemonConnectionReader,
     UnixSocketDaemonConnectionWriter,
 )> {
-    let socket = UnixStream::connect("/opt/piavpn/var/daemon.sock")?;
-    socket.set_nonblocking(true)?;
-    Ok((BufReader::new(socket.try_clone()?), BufWriter::new(socket)))
+    let socket = connect(path, timeout)?;
+    socket.set_nonblocking(nonblocking)?;
+    Ok((BufReader::new(socket.try_clone()?), socket))
+}
+
+fn connect(path: &Path, timeout: Option<Duration>) -> io::Result<UnixStream> {
+    let Some(timeout) = timeout else {
+        return UnixStream::connect(path);
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        match UnixStream::connect(path) {
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+                ) =>
+            {
+                if Instant::now() >= deadline {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("timed out connecting to {}: {err}", path.display()),
+                    ));
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            res => return res,
+        }
+    }
 }
 
 pub type UnixSocketDaemonConnectionReader = BufReader<UnixStream>;
-pub type UnixSocketDaemonConnectionWriter = BufWriter<UnixStream>;
+/// Unbuffered, since the session keeps track of how much of its output has been written.
+pub type UnixSocketDaemonConnectionWriter = UnixStream;
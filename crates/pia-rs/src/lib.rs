@@ -1,8 +1,23 @@
 pub mod event;
 mod util;
 pub use util::ServerCode;
+#[cfg(feature = "tokio")]
+mod async_connection;
 mod connection;
+#[cfg(feature = "diagnostics")]
+mod diagnostics;
+mod error;
 mod jsonrpc;
 mod lang;
+mod model;
 
-pub use connection::{take_connection, DaemonConnection};
+#[cfg(feature = "tokio")]
+pub use async_connection::AsyncDaemonConnection;
+pub use connection::{
+    take_connection, DaemonConnection, DaemonConnectionBuilder, DEFAULT_CALL_TIMEOUT,
+    DEFAULT_SOCKET_PATH, SOCKET_PATH_ENV_VAR,
+};
+#[cfg(feature = "diagnostics")]
+pub use diagnostics::{redacted_state, write_diagnostics_bundle};
+pub use error::{DecodeError, Error};
+pub use model::{Changes, DaemonModel, DedicatedIp, IncompleteObject, SubscriptionId, ValueChange};
@@ -0,0 +1,435 @@
+use std::{
+    fmt,
+    sync::mpsc,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    event::daemon::{
+        AccountData, DaemonSettings, DaemonState, DataEventParam, InnerData, MissingField,
+        PartialAccount, PartialDaemonData, PartialDaemonState, PartialObject, PartialSettings,
+        UnixTime,
+    },
+    ServerCode,
+};
+
+/// The daemon's properties, kept up to date by applying `data` events to it.
+///
+/// Each object is `None` until the daemon first sends it, which it does right after connecting.
+/// If the first update of an object is missing fields, it's held back and combined with later
+/// updates until every field has been sent.
+///
+/// To be told when specific properties change, use [`subscribe`](Self::subscribe) or
+/// [`subscribe_channel`](Self::subscribe_channel).
+#[derive(Default)]
+pub struct DaemonModel {
+    account: Option<AccountData>,
+    data: Option<InnerData>,
+    settings: Option<DaemonSettings>,
+    state: Option<DaemonState>,
+
+    pending_account: Option<PartialAccount>,
+    pending_data: Option<PartialDaemonData>,
+    pending_settings: Option<PartialSettings>,
+    pending_state: Option<PartialDaemonState>,
+
+    last_subscription_id: u64,
+    subscriptions: Vec<Subscription>,
+}
+
+impl fmt::Debug for DaemonModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DaemonModel")
+            .field("account", &self.account)
+            .field("data", &self.data)
+            .field("settings", &self.settings)
+            .field("state", &self.state)
+            .field("pending_account", &self.pending_account)
+            .field("pending_data", &self.pending_data)
+            .field("pending_settings", &self.pending_settings)
+            .field("pending_state", &self.pending_state)
+            .field("subscriptions", &self.subscriptions.len())
+            .finish()
+    }
+}
+
+impl DaemonModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn account(&self) -> Option<&AccountData> {
+        self.account.as_ref()
+    }
+    pub fn data(&self) -> Option<&InnerData> {
+        self.data.as_ref()
+    }
+    pub fn settings(&self) -> Option<&DaemonSettings> {
+        self.settings.as_ref()
+    }
+    pub fn state(&self) -> Option<&DaemonState> {
+        self.state.as_ref()
+    }
+
+    /// The dedicated IPs that have been added, along with when they expire if the daemon says.
+    ///
+    /// While the daemon is warning that a dedicated IP will expire soon
+    /// ([`DaemonState::dedicated_ip_expiring`]), the one expiring next has
+    /// [`expiring_in_days`](DedicatedIp::expiring_in_days) set. The daemon doesn't say which one
+    /// that is, so if there's more than one and their expiration times aren't known, none of them
+    /// have it set.
+    pub fn dedicated_ips(&self) -> Vec<DedicatedIp> {
+        let Some(state) = self.state() else {
+            return Vec::new();
+        };
+        let account_dips = self
+            .account()
+            .map_or(&[][..], |account| &account.dedicated_ips);
+        let mut dips = state
+            .dedicated_ip_locations
+            .iter()
+            .map(|location| DedicatedIp {
+                region: location.id.clone(),
+                ip: location.dedicated_ip.clone(),
+                expires: account_dips
+                    .iter()
+                    .find(|dip| dip.id == location.id && dip.expire != 0)
+                    .map(|dip| UnixTime(dip.expire.saturating_mul(1000))),
+                expiring_in_days: None,
+            })
+            .collect::<Vec<_>>();
+
+        if state.dedicated_ip_expiring.0.is_some() {
+            let days = u32::try_from(state.dedicated_ip_days_remaining).unwrap_or(0);
+            let next = if dips.len() == 1 {
+                Some(0)
+            } else {
+                dips.iter()
+                    .enumerate()
+                    .filter_map(|(i, dip)| Some((dip.expires?.0, i)))
+                    .min()
+                    .map(|(_, i)| i)
+            };
+            if let Some(next) = next {
+                dips[next].expiring_in_days = Some(days);
+            }
+        }
+        dips
+    }
+
+    /// Applies the params of a [`DaemonEvent::Data`](crate::event::daemon::DaemonEvent::Data)
+    /// event, returning which properties changed. Subscribers are notified before this returns.
+    ///
+    /// Each object is applied on its own. An object that's still missing fields is listed in
+    /// [`Changes::incomplete`] and kept until later events fill them in.
+    pub fn apply(&mut self, param: DataEventParam) -> Changes {
+        let changes = self.apply_objects(param);
+        self.notify();
+        changes
+    }
+
+    /// Calls `callback` with the old and new value whenever the value returned by `selector`
+    /// changes, e.g. `model.subscribe(|model| model.state().map(|state| state.connection_state), ..)`.
+    ///
+    /// `selector` is run on every update, so it should be cheap.
+    pub fn subscribe<T, S, F>(&mut self, selector: S, mut callback: F) -> SubscriptionId
+    where
+        T: PartialEq + Send + 'static,
+        S: Fn(&DaemonModel) -> T + Send + 'static,
+        F: FnMut(&T, &T) + Send + 'static,
+    {
+        let mut last = selector(self);
+        self.add_subscription(Box::new(move |model| {
+            let new = selector(model);
+            if new != last {
+                callback(&last, &new);
+                last = new;
+            }
+            true
+        }))
+    }
+
+    /// Like [`subscribe`](Self::subscribe), but sends the changes to a channel. The subscription
+    /// is removed once the receiver is dropped.
+    pub fn subscribe_channel<T, S>(&mut self, selector: S) -> mpsc::Receiver<ValueChange<T>>
+    where
+        T: PartialEq + Clone + Send + 'static,
+        S: Fn(&DaemonModel) -> T + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let mut last = selector(self);
+        self.add_subscription(Box::new(move |model| {
+            let new = selector(model);
+            if new == last {
+                return true;
+            }
+            let old = std::mem::replace(&mut last, new.clone());
+            sender.send(ValueChange { old, new }).is_ok()
+        }));
+        receiver
+    }
+
+    /// Removes a subscription. Returns whether it existed.
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let len = self.subscriptions.len();
+        self.subscriptions
+            .retain(|subscription| subscription.id != id);
+        self.subscriptions.len() != len
+    }
+
+    fn add_subscription(&mut self, notify: NotifyFn) -> SubscriptionId {
+        self.last_subscription_id += 1;
+        let id = SubscriptionId(self.last_subscription_id);
+        self.subscriptions.push(Subscription { id, notify });
+        id
+    }
+
+    fn notify(&mut self) {
+        let mut subscriptions = std::mem::take(&mut self.subscriptions);
+        subscriptions.retain_mut(|subscription| (subscription.notify)(self));
+        self.subscriptions = subscriptions;
+    }
+
+    fn apply_objects(&mut self, param: DataEventParam) -> Changes {
+        let mut incomplete = Vec::new();
+        let mut record = |object, res: Result<_, _>| {
+            res.unwrap_or_else(|missing| {
+                incomplete.push(IncompleteObject { object, missing });
+                Vec::new()
+            })
+        };
+        let account = record(
+            "account",
+            update(&mut self.account, &mut self.pending_account, param.account),
+        );
+        let data = record(
+            "data",
+            update(
+                &mut self.data,
+                &mut self.pending_data,
+                param.data.map(|data| *data),
+            ),
+        );
+        let settings = record(
+            "settings",
+            update(
+                &mut self.settings,
+                &mut self.pending_settings,
+                param.settings.map(|settings| *settings),
+            ),
+        );
+        let state = record(
+            "state",
+            update(
+                &mut self.state,
+                &mut self.pending_state,
+                param.state.map(|state| *state),
+            ),
+        );
+        Changes {
+            account,
+            data,
+            settings,
+            state,
+            incomplete,
+        }
+    }
+}
+
+/// Applies `partial` to `current`, or if there's no current value yet, adds it to `pending` and
+/// converts that once it has every field.
+fn update<P: PartialObject>(
+    current: &mut Option<P::Full>,
+    pending: &mut Option<P>,
+    partial: Option<P>,
+) -> Result<Vec<&'static str>, MissingField> {
+    let Some(partial) = partial else {
+        return Ok(Vec::new());
+    };
+    if let Some(current) = current {
+        return Ok(P::apply_to(current, partial));
+    }
+    let partial = match pending.take() {
+        Some(mut pending) => {
+            pending.merge(partial);
+            pending
+        }
+        None => partial,
+    };
+    if let Some(missing) = partial.missing_field() {
+        *pending = Some(partial);
+        return Err(missing);
+    }
+    *current = Some(partial.try_into()?);
+    Ok(P::fields().to_vec())
+}
+
+/// Names of the properties that changed in each object, as returned by [`DaemonModel::apply`].
+/// These are the Rust field names, e.g. `connection_state`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Changes {
+    pub account: Vec<&'static str>,
+    pub data: Vec<&'static str>,
+    pub settings: Vec<&'static str>,
+    pub state: Vec<&'static str>,
+    /// Objects that haven't been sent with all of their fields yet, so are still `None`.
+    pub incomplete: Vec<IncompleteObject>,
+}
+
+impl Changes {
+    /// Whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.account.is_empty()
+            && self.data.is_empty()
+            && self.settings.is_empty()
+            && self.state.is_empty()
+    }
+}
+
+/// An object that couldn't be applied yet because it's still missing a field, as returned in
+/// [`Changes::incomplete`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IncompleteObject {
+    /// The object's name in the `data` event, e.g. `state`.
+    pub object: &'static str,
+    pub missing: MissingField,
+}
+
+impl fmt::Display for IncompleteObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.object, self.missing)
+    }
+}
+impl std::error::Error for IncompleteObject {}
+
+/// A dedicated IP, as returned by [`DaemonModel::dedicated_ips`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DedicatedIp {
+    /// ID of the region created for this dedicated IP.
+    pub region: ServerCode,
+    pub ip: Option<String>,
+    pub expires: Option<UnixTime>,
+    /// Days left before this expires, while the daemon is warning about it. See
+    /// [`DaemonModel::dedicated_ips`].
+    pub expiring_in_days: Option<u32>,
+}
+
+impl DedicatedIp {
+    /// How long until this expires, or zero if it already has. `None` if the expiration time
+    /// isn't known.
+    pub fn time_remaining(&self) -> Option<Duration> {
+        let expires = SystemTime::UNIX_EPOCH.checked_add(Duration::from_millis(self.expires?.0))?;
+        Some(
+            expires
+                .duration_since(SystemTime::now())
+                .unwrap_or_default(),
+        )
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.time_remaining() == Some(Duration::ZERO)
+    }
+}
+
+/// Identifies a subscription made with [`DaemonModel::subscribe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// A value selected by [`DaemonModel::subscribe_channel`] before and after it changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueChange<T> {
+    pub old: T,
+    pub new: T,
+}
+
+/// Checks a subscription for changes. Returns `false` once the subscriber is gone.
+type NotifyFn = Box<dyn FnMut(&DaemonModel) -> bool + Send>;
+
+struct Subscription {
+    id: SubscriptionId,
+    notify: NotifyFn,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::daemon::PartialSettings;
+
+    const ACCOUNT_WITHOUT_ACTIVE: &str = r#"{"account":{
+        "canceled": false,
+        "daysRemaining": 30,
+        "expirationTime": 1700000000000,
+        "expireAlert": false,
+        "expired": false,
+        "loggedIn": true,
+        "needsPayment": false,
+        "plan": "monthly",
+        "recurring": true,
+        "renewURL": "",
+        "renewable": true,
+        "username": "p0000000"
+    }}"#;
+
+    fn param(json: &str) -> DataEventParam {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn incomplete_object_is_held_back() {
+        let mut model = DaemonModel::new();
+        let changes = model.apply(param(r#"{"account":{"active":true,"plan":"yearly"}}"#));
+        assert_eq!(
+            changes.incomplete,
+            [IncompleteObject {
+                object: "account",
+                missing: MissingField("canceled"),
+            }]
+        );
+        assert!(changes.is_empty());
+        assert_eq!(model.account(), None);
+
+        // later values win, earlier ones fill in the gaps
+        let changes = model.apply(param(ACCOUNT_WITHOUT_ACTIVE));
+        assert_eq!(changes.incomplete, []);
+        assert_eq!(changes.account, AccountData::FIELDS);
+        let account = model.account().unwrap();
+        assert!(account.active);
+        assert_eq!(account.plan, "monthly");
+        assert_eq!(account.dedicated_ips, []);
+    }
+
+    #[test]
+    fn apply_returns_changed_fields() {
+        let mut model = DaemonModel::new();
+        model.apply(param(r#"{"account":{"active":true}}"#));
+        model.apply(param(ACCOUNT_WITHOUT_ACTIVE));
+
+        let changes = model.apply(param(
+            r#"{"account":{"active":true,"plan":"yearly","daysRemaining":30}}"#,
+        ));
+        assert_eq!(
+            changes,
+            Changes {
+                account: vec!["plan"],
+                ..Changes::default()
+            }
+        );
+        assert_eq!(model.account().unwrap().plan, "yearly");
+    }
+
+    #[test]
+    fn explicit_null_is_some_none() {
+        let partial: PartialSettings = serde_json::from_str(r#"{"debugLogging":null}"#).unwrap();
+        assert_eq!(partial.debug_logging, Some(None));
+        let partial: PartialSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(partial.debug_logging, None);
+
+        // merging keeps the explicit null
+        let mut merged: PartialSettings =
+            serde_json::from_str(r#"{"debugLogging":["qt.*=true"]}"#).unwrap();
+        merged.merge(serde_json::from_str(r#"{"debugLogging":null}"#).unwrap());
+        assert_eq!(merged.debug_logging, Some(None));
+        merged.merge(PartialSettings::default());
+        assert_eq!(merged.debug_logging, Some(None));
+    }
+}
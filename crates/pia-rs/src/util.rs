@@ -8,13 +8,35 @@ use std::{
 
 use serde_derive::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq)]
+/// A region ID, e.g. `us_california`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Hash, PartialEq, Eq)]
 #[serde(transparent)]
 pub struct ServerCode(String);
 
+impl ServerCode {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+impl From<String> for ServerCode {
+    fn from(code: String) -> Self {
+        Self(code)
+    }
+}
+impl From<&str> for ServerCode {
+    fn from(code: &str) -> Self {
+        Self(code.to_owned())
+    }
+}
+impl fmt::Display for ServerCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
 pub type ServerMap<T> = HashMap<ServerCode, T>;
 
-#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq)]
 #[serde(transparent)]
 pub struct CountryCode(String);
 
@@ -1,6 +1,12 @@
-use std::io;
+use std::error::Error;
 
-use pia_rs::event::daemon::DaemonState;
+use pia_rs::{
+    event::{
+        client::Call,
+        daemon::{DaemonState, LocationChoice, SnoozeState},
+    },
+    DaemonModel, ServerCode,
+};
 use ratatui::{
     crossterm::{self, event::MouseButton},
     layout::Alignment,
@@ -10,12 +16,17 @@ use ratatui::{
     Frame,
 };
 
+/// How long pressing `s` snoozes the VPN for.
+const SNOOZE_DURATION: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
 #[derive(Debug)]
 pub struct App {
     conn: pia_rs::DaemonConnection,
 
     is_running: bool,
-    state: Option<DaemonState>,
+    model: DaemonModel,
+    /// Index into [`App::locations`] of the highlighted region.
+    selected: usize,
 }
 impl App {
     pub fn render_frame(&self, frame: &mut Frame) {
@@ -24,34 +35,20 @@ impl App {
     pub fn is_running(&self) -> bool {
         self.is_running
     }
-    pub fn handle_events(&mut self) -> io::Result<()> {
+    pub fn handle_events(&mut self) -> Result<(), Box<dyn Error>> {
         loop {
             let res = self.conn.poll();
             match res {
-                Ok(e) => match *e {
-                    pia_rs::event::daemon::DaemonEvent::Data([data]) => match self.state {
-                        None => {
-                            self.state = Some(serde_json::from_value(serde_json::Value::Object(
-                                data.state.unwrap(),
-                            ))?);
-                        }
-                        Some(ref mut state) => {
-                            if let Some(new_state) = data.state {
-                                let serde_json::Value::Object(mut object) =
-                                    serde_json::to_value(&state)?
-                                else {
-                                    unreachable!();
-                                };
-                                for (key, value) in new_state {
-                                    object[&key] = value;
-                                }
-                                *state = serde_json::from_value(serde_json::Value::Object(object))?;
-                            }
-                        }
-                    },
-                },
-                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
-                Err(err) => return Err(err),
+                Ok(e) => {
+                    let pia_rs::event::daemon::DaemonEvent::Data([data]) = *e else {
+                        // replies and unknown notifications aren't used yet
+                        continue;
+                    };
+                    // objects missing fields are kept until the rest arrives
+                    self.model.apply(data);
+                }
+                Err(err) if err.is_would_block() => break,
+                Err(err) => return Err(err.into()),
             }
         }
         use crossterm::event;
@@ -66,20 +63,65 @@ impl App {
                 event::Event::Key(event::KeyEvent {
                     code: event::KeyCode::Char(' '),
                     ..
-                }) => match self.state {
+                }) => match self.model.state() {
                     Some(DaemonState {
                         connection_state: pia_rs::event::daemon::ConnectionState::Disconnected,
                         ..
                     }) => {
-                        self.conn
-                            .send(pia_rs::event::client::ClientEvent::ConnectVPN)?;
+                        self.send(pia_rs::event::client::ClientEvent::ConnectVPN)?;
                     }
                     Some(DaemonState {
                         connection_state: pia_rs::event::daemon::ConnectionState::Connected,
                         ..
                     }) => {
-                        self.conn
-                            .send(pia_rs::event::client::ClientEvent::DisconnectVPN)?;
+                        self.send(pia_rs::event::client::ClientEvent::DisconnectVPN)?;
+                    }
+                    _ => (),
+                },
+                event::Event::Key(event::KeyEvent {
+                    code: event::KeyCode::Up | event::KeyCode::Char('k'),
+                    ..
+                }) => {
+                    self.selected = self.selected.saturating_sub(1);
+                }
+                event::Event::Key(event::KeyEvent {
+                    code: event::KeyCode::Down | event::KeyCode::Char('j'),
+                    ..
+                }) => {
+                    let len = self.locations().len();
+                    self.selected = (self.selected + 1).min(len.saturating_sub(1));
+                }
+                event::Event::Key(event::KeyEvent {
+                    code: event::KeyCode::Enter,
+                    ..
+                }) => {
+                    if let Some(&code) = self.locations().get(self.selected) {
+                        self.connect_to(LocationChoice::Server(code.clone()))?;
+                    }
+                }
+                event::Event::Key(event::KeyEvent {
+                    code: event::KeyCode::Char('a'),
+                    ..
+                }) => {
+                    self.connect_to(LocationChoice::Auto)?;
+                }
+                event::Event::Key(event::KeyEvent {
+                    code: event::KeyCode::Char('s'),
+                    ..
+                }) => match self
+                    .model
+                    .state()
+                    .map(|state| (state.connection_state, state.snooze_end_time))
+                {
+                    // like the official client, only a connected VPN can be snoozed
+                    Some((
+                        pia_rs::event::daemon::ConnectionState::Connected,
+                        SnoozeState::Inactive,
+                    )) => {
+                        self.send(Call::start_snooze(SNOOZE_DURATION).into_event())?;
+                    }
+                    Some((_, SnoozeState::Until(_))) => {
+                        self.send(Call::stop_snooze().into_event())?;
                     }
                     _ => (),
                 },
@@ -89,6 +131,33 @@ impl App {
 
         Ok(())
     }
+    /// All regions, in the same order as the daemon's regions list.
+    fn locations(&self) -> Vec<&ServerCode> {
+        let Some(state) = self.model.state() else {
+            return Vec::new();
+        };
+        state
+            .grouped_locations
+            .iter()
+            .flat_map(|country| &country.locations)
+            .chain(&state.dedicated_ip_locations)
+            .map(|location| &location.id)
+            .collect()
+    }
+    fn connect_to(&mut self, location: LocationChoice) -> Result<(), pia_rs::Error> {
+        // sent without waiting for replies so the UI doesn't freeze
+        self.send(Call::set_location(location).into_event())?;
+        self.send(pia_rs::event::client::ClientEvent::ConnectVPN)
+    }
+    /// Sends an event without blocking. Whatever doesn't fit in the socket yet is written by the
+    /// polling in [`handle_events`](Self::handle_events).
+    fn send(&mut self, event: pia_rs::event::client::ClientEvent) -> Result<(), pia_rs::Error> {
+        match self.conn.send(event) {
+            // the daemon is busy; drop the input instead of piling onto it
+            Err(pia_rs::Error::Backpressure { .. }) => Ok(()),
+            res => res,
+        }
+    }
 }
 impl Default for App {
     fn default() -> Self {
@@ -96,7 +165,8 @@ impl Default for App {
         Self {
             conn,
             is_running: true,
-            state: None,
+            model: DaemonModel::new(),
+            selected: 0,
         }
     }
 }
@@ -105,10 +175,25 @@ impl Widget for &App {
     where
         Self: Sized,
     {
+        let main_info = Rect::from((area.as_position(), Size::new(64, 16))).intersection(area);
         MainInfo {
-            state: self.state.as_ref(),
+            state: self.model.state(),
         }
-        .render(Rect::from((area.as_position(), Size::new(64, 16))), buf)
+        .render(main_info, buf);
+        Regions {
+            state: self.model.state(),
+            locations: self.locations(),
+            selected: self.selected,
+        }
+        .render(
+            Rect {
+                x: main_info.right(),
+                width: 40,
+                ..area
+            }
+            .intersection(area),
+            buf,
+        );
     }
 }
 
@@ -118,9 +203,9 @@ struct MainInfo<'a> {
 
 impl Widget for MainInfo<'_> {
     fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer) {
-        let title = Title::from("Main Info".bold());
+        let title = Title::from("Main Info (space: connect, s: snooze)".bold());
         let block = Block::bordered().title(title.alignment(Alignment::Center));
-        Paragraph::new(Text::from(vec![
+        let mut lines = vec![
             Line::from(vec![
                 "Connection state: ".into(),
                 self.state.map_or("...".into(), |state| {
@@ -153,8 +238,72 @@ impl Widget for MainInfo<'_> {
                         None => "N/A".gray(),
                     }),
             ]),
-        ]))
-        .block(block)
-        .render(area, buf);
+        ];
+        match self.state.map(|state| state.snooze_end_time) {
+            Some(SnoozeState::Starting) => {
+                lines.push(Line::from(vec!["Snoozed: ".into(), "starting...".yellow()]));
+            }
+            Some(snooze @ SnoozeState::Until(_)) => {
+                let secs = snooze.remaining().unwrap_or_default().as_secs();
+                lines.push(Line::from(vec![
+                    "Snoozed: ".into(),
+                    format!("{}:{:02} remaining", secs / 60, secs % 60).yellow(),
+                ]));
+            }
+            _ => (),
+        }
+        if let Some(state) = self.state {
+            use pia_rs::event::daemon::Severity;
+            for notification in state.active_notifications() {
+                let string = notification.kind.to_string();
+                lines.push(Line::from(match notification.severity {
+                    Severity::Info => string.gray(),
+                    Severity::Warning => string.yellow(),
+                    Severity::Error => string.red(),
+                }));
+            }
+        }
+        Paragraph::new(Text::from(lines))
+            .block(block)
+            .render(area, buf);
+    }
+}
+
+struct Regions<'a> {
+    state: Option<&'a DaemonState>,
+    locations: Vec<&'a ServerCode>,
+    selected: usize,
+}
+
+impl Widget for Regions<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let title = Title::from("Regions (enter: connect, a: auto)".bold());
+        let block = Block::bordered().title(title.alignment(Alignment::Center));
+        let chosen = self
+            .state
+            .and_then(|state| state.vpn_locations.chosen_location.as_ref())
+            .map(|location| &location.id);
+
+        // keep the selection in view
+        let height = block.inner(area).height as usize;
+        let skip = (self.selected + 1).saturating_sub(height);
+        let lines = self
+            .locations
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .map(|(i, &code)| {
+                let marker = if Some(code) == chosen { "* " } else { "  " };
+                let line = Line::from(format!("{marker}{code}"));
+                if i == self.selected {
+                    line.reversed()
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<_>>();
+        Paragraph::new(Text::from(lines))
+            .block(block)
+            .render(area, buf);
     }
 }